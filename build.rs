@@ -1,11 +1,10 @@
-use prost_build;
 use std::io::Result;
 fn main() -> Result<()> {
     let mut config = prost_build::Config::new();
     config.type_attribute(
         ".",
         //"\n",
-        vec![
+        [
             "#[derive(serde::Deserialize, serde::Serialize)]",
             "#[serde(deny_unknown_fields, default)]",
         ]
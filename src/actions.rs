@@ -3,11 +3,20 @@
 use crate::config::{self, Match};
 use crate::gh::PullRequest;
 
+/// Mark the authors from `bot_logins` as bots and mute the PRs by bots, then apply the actions in order. An action can
+/// unmute the PRs by bots.
 pub fn apply_actions(config: &config::Config, prs: &Vec<PullRequest>) -> Vec<PullRequest> {
     let mut final_prs: Vec<PullRequest> = Vec::new();
 
     for pr in prs {
-        let mut current_pr = Some(pr.clone());
+        let mut pr = pr.clone();
+        if config.bot_logins.contains(&pr.author.login) {
+            pr.author.is_bot = true;
+        }
+        if pr.author.is_bot {
+            pr.meta.default_mute = true;
+        }
+        let mut current_pr = Some(pr);
         for action in &config.actions {
             current_pr = match current_pr {
                 Some(ref pr) => action.act(pr),
@@ -46,10 +55,8 @@ impl Act for config::Action {
 
 /// Check if ALL of the clauses in [Match] match the pr.
 fn is_match(m: &Match, pr: &PullRequest) -> bool {
-    if !m.title.is_empty() {
-        if !pr.title.contains(&m.title) {
-            return false;
-        }
+    if !m.title.is_empty() && !pr.title.contains(&m.title) {
+        return false;
     }
     if !m.reviewer.is_empty() {
         let has_reviewer = pr.review_requests.iter().any(|r| {
@@ -61,17 +68,26 @@ fn is_match(m: &Match, pr: &PullRequest) -> bool {
             return false;
         }
     }
-    if !m.author.is_empty() {
-        if !pr.author.login.contains(&m.author) {
-            return false;
-        }
+    if !m.author.is_empty() && !pr.author.login.contains(&m.author) {
+        return false;
     }
-    if !m.repository.is_empty() {
-        if !pr.repository.name.contains(&m.repository)
-            && !pr.repository.name_with_owner.contains(&m.repository)
-        {
-            return false;
-        }
+    if !m.label.is_empty() && !pr.labels.iter().any(|l| l.contains(&m.label)) {
+        return false;
+    }
+    if !m.assignee.is_empty() && !pr.assignees.iter().any(|a| a.contains(&m.assignee)) {
+        return false;
+    }
+    if !m.milestone.is_empty() && !pr.milestone.contains(&m.milestone) {
+        return false;
+    }
+    if m.bot.is_some_and(|bot| bot != pr.author.is_bot) {
+        return false;
+    }
+    if !m.repository.is_empty()
+        && !pr.repository.name.contains(&m.repository)
+        && !pr.repository.name_with_owner.contains(&m.repository)
+    {
+        return false;
     }
     true
 }
@@ -1,15 +1,63 @@
+use crate::links;
 use anyhow::Result;
 use std::fs;
 
 include!(concat!(env!("OUT_DIR"), "/ffgh_config_proto_types.rs"));
 
+pub const DRAFTS_EXCLUDE: &str = "exclude";
+pub const DRAFTS_INCLUDE: &str = "include";
+pub const DRAFTS_ONLY: &str = "only";
+pub const KIND_PR: &str = "pr";
+pub const KIND_ISSUE: &str = "issue";
+pub const REVIEW_REQUESTED_DIRECT: &str = "direct";
+pub const REVIEW_REQUESTED_TEAM: &str = "team";
+
 impl Config {
+    /// Read the config, failing if a query has invalid options, so that the sync does not retry it forever.
     pub fn from_file(path: &str) -> Result<Self> {
         let content = fs::read_to_string(path)?;
         let config: Config = serde_yaml::from_str(&content)?;
+        let problems: Vec<String> = config
+            .queries
+            .iter()
+            .flat_map(|query| {
+                query
+                    .option_problems()
+                    .into_iter()
+                    .map(|problem| format!("query '{}': {}", query.query_name, problem))
+            })
+            .collect();
+        if !problems.is_empty() {
+            return Err(anyhow::anyhow!("Invalid config: {}", problems.join("; ")));
+        }
         Ok(config)
     }
 
+    /// Problems with the config, like malformed queries, prefixed with the name of the query. Empty if the config is
+    /// fine.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        for query in &self.queries {
+            for problem in query.validate() {
+                problems.push(format!("query '{}': {}", query.query_name, problem));
+            }
+        }
+        problems.extend(links::validate(&self.link_templates));
+        problems
+    }
+
+    /// Things in the config that may be mistakes but do not prevent using it, like search qualifiers unknown to ffgh,
+    /// prefixed with the name of the query.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        for query in &self.queries {
+            for warning in query.warnings() {
+                warnings.push(format!("query '{}': {}", query.query_name, warning));
+            }
+        }
+        warnings
+    }
+
     pub fn default_yaml() -> &'static str {
         r#"
 queries:
@@ -41,3 +89,256 @@ annotations:
 "#
     }
 }
+
+/// Qualifiers of GitHub issue and PR search. GitHub adds new ones from time to time, so the others are only warned
+/// about.
+const KNOWN_QUALIFIERS: &[&str] = &[
+    "archived",
+    "assignee",
+    "author",
+    "base",
+    "closed",
+    "commenter",
+    "comments",
+    "created",
+    "draft",
+    "has",
+    "head",
+    "in",
+    "interactions",
+    "involves",
+    "is",
+    "label",
+    "language",
+    "linked",
+    "mentions",
+    "merged",
+    "milestone",
+    "no",
+    "org",
+    "parent-issue",
+    "project",
+    "reactions",
+    "reason",
+    "repo",
+    "review",
+    "review-requested",
+    "reviewed-by",
+    "sha",
+    "sort",
+    "state",
+    "status",
+    "team",
+    "team-review-requested",
+    "type",
+    "updated",
+    "user",
+    "user-review-requested",
+];
+
+impl Query {
+    /// The search qualifiers of the query, from `search`, or converted from the `--key=value` arguments of
+    /// `github_arg`, which may also be given as `key:value`. The type and state qualifiers are not included.
+    pub fn search_qualifiers(&self) -> Result<String> {
+        match (self.search.is_empty(), self.github_arg.is_empty()) {
+            (false, true) => Ok(split_search(&self.search)?.join(" ")),
+            (true, false) => {
+                let qualifiers = split_search(&self.github_arg)?
+                    .into_iter()
+                    .map(|arg| {
+                        let key_value = arg.strip_prefix("--").unwrap_or(&arg);
+                        match key_value.split_once(['=', ':']) {
+                            Some((key, value)) if !key.is_empty() && !value.is_empty() => {
+                                Ok(format!("{}:{}", key, value))
+                            }
+                            _ => Err(anyhow::anyhow!(
+                                "malformed github_arg '{}', expected --key=value or key:value",
+                                arg
+                            )),
+                        }
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(qualifiers.join(" "))
+            }
+            (false, false) => Err(anyhow::anyhow!("both search and github_arg are set")),
+            (true, true) => Err(anyhow::anyhow!("neither search nor github_arg is set")),
+        }
+    }
+
+    /// Problems with the query. The search qualifiers must have a value, and not override the type and state, which
+    /// come from `kind` and `drafts`.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = self.option_problems();
+        let qualifiers = match self.search_qualifiers() {
+            Ok(qualifiers) => qualifiers,
+            Err(e) => {
+                problems.push(e.to_string());
+                return problems;
+            }
+        };
+        for (term, key, value) in qualifier_terms(&qualifiers) {
+            if value.is_empty() {
+                problems.push(format!("qualifier '{}' without value", term));
+            } else if matches!(key.as_str(), "type" | "state" | "draft")
+                || (key == "is"
+                    && matches!(
+                        value.to_lowercase().as_str(),
+                        "pr" | "issue" | "open" | "closed" | "merged" | "unmerged" | "draft"
+                    ))
+            {
+                problems.push(format!(
+                    "unsupported qualifier '{}', the type and state come from kind and drafts",
+                    term
+                ));
+            }
+        }
+        problems
+    }
+
+    /// The search qualifiers that are not in [KNOWN_QUALIFIERS], likely typos.
+    pub fn warnings(&self) -> Vec<String> {
+        let Ok(qualifiers) = self.search_qualifiers() else {
+            return Vec::new();
+        };
+        qualifier_terms(&qualifiers)
+            .into_iter()
+            .filter(|(_, key, _)| !KNOWN_QUALIFIERS.contains(&key.as_str()))
+            .map(|(term, _, _)| format!("unknown qualifier '{}'", term))
+            .collect()
+    }
+
+    /// Problems with the options that make the query unusable: an unknown kind, drafts or review_requested.
+    fn option_problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !matches!(self.kind.as_str(), "" | KIND_PR | KIND_ISSUE) {
+            problems.push(format!("invalid kind '{}'", self.kind));
+        }
+        if !matches!(
+            self.drafts.as_str(),
+            "" | DRAFTS_EXCLUDE | DRAFTS_INCLUDE | DRAFTS_ONLY
+        ) {
+            problems.push(format!("invalid drafts '{}'", self.drafts));
+        }
+        if !matches!(
+            self.review_requested.as_str(),
+            "" | REVIEW_REQUESTED_DIRECT | REVIEW_REQUESTED_TEAM
+        ) {
+            problems.push(format!(
+                "invalid review_requested '{}'",
+                self.review_requested
+            ));
+        }
+        problems
+    }
+}
+
+/// The `key:value` terms of the search qualifiers, as the term, the lowercase key without negation, and the value.
+/// Free text and quoted terms are skipped.
+fn qualifier_terms(qualifiers: &str) -> Vec<(String, String, String)> {
+    split_search(qualifiers)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|term| {
+            let (key, value) = term.trim_start_matches('-').split_once(':')?;
+            if key.contains('"') {
+                return None;
+            }
+            let (key, value) = (key.to_lowercase(), value.to_string());
+            Some((term, key, value))
+        })
+        .collect()
+}
+
+/// Split a search string into terms on whitespace, keeping quoted parts like `label:"good first issue"` together.
+fn split_search(search: &str) -> Result<Vec<String>> {
+    let mut terms = Vec::new();
+    let mut term = String::new();
+    let mut quoted = false;
+    for ch in search.chars() {
+        match ch {
+            '"' => {
+                quoted = !quoted;
+                term.push(ch);
+            }
+            ch if ch.is_whitespace() && !quoted => {
+                if !term.is_empty() {
+                    terms.push(std::mem::take(&mut term));
+                }
+            }
+            ch => term.push(ch),
+        }
+    }
+    if quoted {
+        return Err(anyhow::anyhow!("unbalanced quotes in '{}'", search));
+    }
+    if !term.is_empty() {
+        terms.push(term);
+    }
+    Ok(terms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(github_arg: &str, search: &str) -> Query {
+        Query {
+            query_name: "Q".to_string(),
+            github_arg: github_arg.to_string(),
+            search: search.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn split_search_keeps_quoted_terms_together() {
+        assert_eq!(
+            split_search(r#"  org:acme label:"good first issue"  -label:wip "#).unwrap(),
+            vec!["org:acme", r#"label:"good first issue""#, "-label:wip"]
+        );
+        assert!(split_search(r#"label:"unbalanced"#).is_err());
+    }
+
+    #[test]
+    fn github_arg_accepts_both_forms() {
+        assert_eq!(
+            query("--review-requested=@me --repo=acme/api", "")
+                .search_qualifiers()
+                .unwrap(),
+            "review-requested:@me repo:acme/api"
+        );
+        assert_eq!(
+            query("review-requested:@me", "")
+                .search_qualifiers()
+                .unwrap(),
+            "review-requested:@me"
+        );
+        assert_eq!(
+            query("--label=a=b -label:wip", "")
+                .search_qualifiers()
+                .unwrap(),
+            "label:a=b -label:wip"
+        );
+        assert!(query("--author", "").search_qualifiers().is_err());
+        assert!(query("", "").search_qualifiers().is_err());
+        assert!(query("--author=@me", "author:@me")
+            .search_qualifiers()
+            .is_err());
+    }
+
+    #[test]
+    fn unknown_qualifiers_are_warnings() {
+        let q = query("", "user-review-requested:@me sha:abc123 frobnicate:yes");
+        assert!(q.validate().is_empty());
+        assert_eq!(q.warnings(), vec!["unknown qualifier 'frobnicate:yes'"]);
+    }
+
+    #[test]
+    fn validate_rejects_type_and_state_qualifiers() {
+        assert_eq!(query("", "is:pr is:open").validate().len(), 2);
+        assert_eq!(query("", "is:locked author:").validate().len(), 1);
+        let mut q = query("--author=@me", "");
+        q.kind = "issues".to_string();
+        assert_eq!(q.validate(), vec!["invalid kind 'issues'"]);
+    }
+}
@@ -1,7 +1,12 @@
 use crate::config::Config;
-use crate::gh::PullRequest;
-use crate::storage::{get_pr_state_flags, UserState, HAS_NEW_COMMENTS, IS_NEW, IS_UPDATED};
-use crate::{actions, util};
+use crate::gh::{ItemKind, PullRequest, ReviewRequestedFrom, TimelineEvent, TimelineEventKind};
+use crate::links::{self, CompiledTemplate};
+use crate::storage::{
+    get_pr_state_flags, get_waiting_on, DepartedPullRequest, DepartureReason, PrState, UserState,
+    WaitingOn, HAS_NEW_COMMENTS, IS_NEW, IS_UPDATED, NEEDS_RE_REVIEW,
+};
+use crate::transport::DEFAULT_HOST;
+use crate::{actions, stack, util};
 use chrono::{Duration, Utc};
 use colored::*;
 use std::collections::HashMap;
@@ -11,6 +16,14 @@ use std::io::Write;
 pub const VIEW_MODE_REGULAR: &str = "regular";
 pub const VIEW_MODE_MUTE_TOP: &str = "mute-top";
 pub const VIEW_MODE_HIDE_MUTE: &str = "hide-mute";
+/// Show the PRs that recently left the results instead of the current ones.
+pub const VIEW_MODE_DEPARTED: &str = "departed";
+/// Show only the PRs waiting on me.
+pub const VIEW_MODE_MY_TURN: &str = "my-turn";
+/// Show only the PRs by bots.
+pub const VIEW_MODE_BOTS: &str = "bots";
+/// Key of the line that collapses the PRs by bots, in place of a URL.
+pub const BOTS_GROUP_KEY: &str = "bots";
 
 const NBSP: &str = "\u{00A0}";
 
@@ -19,6 +32,9 @@ pub fn cycle_view_mode(mode: &str) -> String {
         VIEW_MODE_REGULAR.to_string(),
         VIEW_MODE_MUTE_TOP.to_string(),
         VIEW_MODE_HIDE_MUTE.to_string(),
+        VIEW_MODE_MY_TURN.to_string(),
+        VIEW_MODE_BOTS.to_string(),
+        VIEW_MODE_DEPARTED.to_string(),
     ];
     util::cycle(mode, &view_modes)
 }
@@ -47,7 +63,7 @@ pub fn print_pull_requests<W: Write>(
         display_priority.insert(query_name.clone(), i);
     }
 
-    prs.sort_by(|a, b| a.number.cmp(&b.number));
+    prs.sort_by_key(|a| a.number);
     prs.sort_by(|a, b| a.repository.name.cmp(&b.repository.name));
     prs.sort_by(|a, b| {
         let a_priority = display_priority
@@ -81,16 +97,31 @@ pub fn print_pull_requests<W: Write>(
             .filter(|pr| !is_mute(user_state, pr))
             .cloned()
             .collect(),
+        VIEW_MODE_MY_TURN => prs
+            .iter()
+            .filter(|pr| get_waiting_on(pr) == WaitingOn::Me)
+            .cloned()
+            .collect(),
+        VIEW_MODE_BOTS => prs.iter().filter(|pr| pr.author.is_bot).cloned().collect(),
         _ => prs.clone(),
     };
 
+    let (filtered_prs, bot_prs): (Vec<_>, Vec<_>) =
+        if config.group_bots && user_state.settings.view_mode != VIEW_MODE_BOTS {
+            filtered_prs.into_iter().partition(|pr| !pr.author.is_bot)
+        } else {
+            (filtered_prs, Vec::new())
+        };
+
+    let filtered_prs = stack::order_as_tree(filtered_prs);
+
     let repo_name_max_len = filtered_prs
         .iter()
-        .map(|pr| pr.repository.name.len())
+        .map(|(pr, _)| pr.repository.name.len())
         .max()
         .unwrap_or(0);
 
-    for pr in &filtered_prs {
+    for (pr, depth) in &filtered_prs {
         let pr_state = user_state.per_url.get(&pr.url).cloned().unwrap_or_default();
         let flags = get_pr_state_flags(pr, &pr_state);
         let mute = is_mute(user_state, pr);
@@ -99,7 +130,7 @@ pub fn print_pull_requests<W: Write>(
 
         if flags & IS_NEW != 0 {
             if mute {
-                flag_string.push_str("N");
+                flag_string.push('N');
             } else {
                 flag_string.push_str(&"N".green().to_string());
             }
@@ -109,7 +140,7 @@ pub fn print_pull_requests<W: Write>(
 
         if flags & IS_UPDATED != 0 {
             if mute {
-                flag_string.push_str("U");
+                flag_string.push('U');
             } else {
                 flag_string.push_str(&"U".magenta().to_string());
             }
@@ -119,7 +150,7 @@ pub fn print_pull_requests<W: Write>(
 
         if flags & HAS_NEW_COMMENTS != 0 {
             if mute {
-                flag_string.push_str("C");
+                flag_string.push('C');
             } else {
                 flag_string.push_str(&"C".bright_yellow().to_string());
             }
@@ -127,19 +158,37 @@ pub fn print_pull_requests<W: Write>(
             flag_string.push_str(NBSP);
         }
 
-        let is_approved = pr
-            .latest_reviews
-            .iter()
-            .any(|r| r.state == "APPROVED");
-        if is_approved {
-            if mute {
-                flag_string.push_str("A");
-            } else {
-                flag_string.push_str(&"A".bright_green().to_string());
-            }
+        let review_flag = if flags & NEEDS_RE_REVIEW != 0 {
+            Some(("↻", Color::Magenta))
+        } else if pr.has_changes_requested() {
+            Some(("R", Color::Red))
+        } else if pr.is_approved() {
+            Some(("A", Color::BrightGreen))
         } else {
-            flag_string.push_str(NBSP);
-        }
+            None
+        };
+        push_flag(&mut flag_string, review_flag, mute);
+
+        let merge_flag = if pr.is_conflicting() {
+            Some(("X", Color::Red))
+        } else if pr.is_blocked() {
+            Some(("B", Color::Yellow))
+        } else {
+            None
+        };
+        push_flag(&mut flag_string, merge_flag, mute);
+
+        let checks = &pr.checks;
+        let ci_flag = if checks.is_failing() {
+            Some(("✗", Color::Red))
+        } else if checks.is_pending() {
+            Some(("•", Color::Yellow))
+        } else if checks.is_success() {
+            Some(("✓", Color::Green))
+        } else {
+            None
+        };
+        push_flag(&mut flag_string, ci_flag, mute);
 
         let note = if !pr_state.note.is_empty() {
             if mute {
@@ -158,17 +207,53 @@ pub fn print_pull_requests<W: Write>(
             .map(|q| q.short_name.as_str())
             .unwrap_or(" ");
         let short_label = if mute {
-            format!("_")
+            "_".to_string()
         } else {
             format!("{} ", short_label)
         };
 
-        let left_parts = vec![
+        let marker = if pr.kind == ItemKind::Issue {
+            Some("[issue]")
+        } else if pr.is_draft {
+            Some("[draft]")
+        } else {
+            None
+        };
+        let title = match marker {
+            None => pr.title.clone(),
+            Some(marker) if mute => format!("{} {}", marker, pr.title),
+            Some(marker) => format!("{} {}", marker.bright_black(), pr.title),
+        };
+        let title = match depth {
+            0 => title,
+            depth => format!("{}└ {}", "  ".repeat(depth - 1), title),
+        };
+
+        let size = format!("{:<2}", pr.size_bucket().unwrap_or(""));
+        let size = match pr.size_bucket() {
+            _ if mute => size,
+            Some("XS" | "S") => size.green().to_string(),
+            Some("M") => size.yellow().to_string(),
+            Some(_) => size.red().to_string(),
+            None => size,
+        };
+
+        let waiting_on = match get_waiting_on(pr) {
+            WaitingOn::Me if mute => "me".to_string(),
+            WaitingOn::Me => "me".bright_cyan().to_string(),
+            WaitingOn::Author if mute => "au".to_string(),
+            WaitingOn::Author => "au".yellow().to_string(),
+            WaitingOn::Others => NBSP.repeat(2),
+        };
+
+        let left_parts = [
             flag_string,
+            waiting_on,
             format!("{:<width$}", pr.repository.name, width = repo_name_max_len),
             short_label,
+            size,
             format!("#{:<5}", pr.number),
-            pr.title.clone(),
+            title,
         ];
 
         let line_left = left_parts.join(" ");
@@ -188,6 +273,120 @@ pub fn print_pull_requests<W: Write>(
         writeln!(writer, "{}", final_line)?;
     }
 
+    if !bot_prs.is_empty() {
+        let line = format!(
+            "{}\t{} {} PR(s) by bots: {}",
+            BOTS_GROUP_KEY,
+            "[bots]".bright_black(),
+            bot_prs.len(),
+            count_by_author(&bot_prs)
+        );
+        writeln!(
+            writer,
+            "{}",
+            join_strings_cap_width(terminal_width, &line, "")
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Preview of the line that collapses the PRs by bots.
+pub fn print_bots_group<W: Write>(
+    writer: &mut W,
+    prs: &[PullRequest],
+    user_state: &UserState,
+) -> Result<(), std::io::Error> {
+    let bot_prs: Vec<&PullRequest> = prs.iter().filter(|pr| pr.author.is_bot).collect();
+    writeln!(
+        writer,
+        "{}",
+        format!(
+            "{} PR(s) by bots, switch to the {} view mode to see them",
+            bot_prs.len(),
+            VIEW_MODE_BOTS
+        )
+        .cyan()
+    )?;
+    writeln!(writer)?;
+    for pr in bot_prs {
+        let line = format!(
+            "{} #{} {} ({})",
+            pr.repository.name_with_owner, pr.number, pr.title, pr.author.login
+        );
+        if is_mute(user_state, pr) {
+            writeln!(writer, "{}", line.bright_black())?;
+        } else {
+            writeln!(writer, "{}", line)?;
+        }
+    }
+    Ok(())
+}
+
+/// Authors of the PRs with the number of PRs, the most frequent first, e.g. "dependabot[bot] (3), renovate[bot]".
+fn count_by_author(prs: &[PullRequest]) -> String {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for pr in prs {
+        match counts.iter_mut().find(|(a, _)| *a == pr.author.login) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((&pr.author.login, 1)),
+        }
+    }
+    counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    counts
+        .into_iter()
+        .map(|(author, count)| match count {
+            1 => author.to_string(),
+            n => format!("{} ({})", author, n),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Print the PRs that recently left the results, the most recent first, with the reason why they left.
+pub fn print_departed_pull_requests<W: Write>(
+    writer: &mut W,
+    terminal_width: usize,
+    departed: &[DepartedPullRequest],
+) -> Result<(), std::io::Error> {
+    let mut departed = departed.to_vec();
+    departed.sort_by_key(|d| std::cmp::Reverse(d.departed_at));
+
+    let repo_name_max_len = departed
+        .iter()
+        .map(|d| d.pr.repository.name.len())
+        .max()
+        .unwrap_or(0);
+
+    let now = Utc::now();
+    for d in &departed {
+        let pr = &d.pr;
+        let reason = format!("{:<9}", d.reason.to_string());
+        let reason = match d.reason {
+            DepartureReason::Merged => reason.magenta().to_string(),
+            DepartureReason::Closed => reason.red().to_string(),
+            DepartureReason::Unmatched => reason.yellow().to_string(),
+            DepartureReason::Deleted => reason.bright_red().to_string(),
+            DepartureReason::Unknown => reason.bright_black().to_string(),
+        };
+        let left_parts = [
+            reason,
+            format!(
+                "{:>3}",
+                PrettyDuration::from_duration(now - d.departed_at).to_string()
+            ),
+            format!("{:<width$}", pr.repository.name, width = repo_name_max_len),
+            format!("#{:<5}", pr.number),
+            pr.title.clone(),
+        ];
+        writeln!(
+            writer,
+            "{}\t{}",
+            pr.url,
+            join_strings_cap_width(terminal_width, &left_parts.join(" "), "")
+        )?;
+    }
+
     Ok(())
 }
 
@@ -195,9 +394,15 @@ pub fn print_show_pull_request<W: Write>(
     writer: &mut W,
     pr_url: &str,
     prs: &[PullRequest],
+    departed: &[DepartedPullRequest],
     user_state: &UserState,
+    link_templates: &[CompiledTemplate],
 ) -> Result<(), std::io::Error> {
-    let pr = prs.iter().find(|p| p.url == pr_url);
+    let departure = departed.iter().find(|d| d.pr.url == pr_url);
+    let pr = prs
+        .iter()
+        .find(|p| p.url == pr_url)
+        .or(departure.map(|d| &d.pr));
     if let Some(pr) = pr {
         let pr_state = user_state.per_url.get(&pr.url).cloned().unwrap_or_default();
         let note = if !pr_state.note.is_empty() {
@@ -218,16 +423,50 @@ pub fn print_show_pull_request<W: Write>(
         if flags & HAS_NEW_COMMENTS != 0 {
             flag_string.push_str(&"COMMENTS".bright_yellow().to_string());
         }
+        if flags & NEEDS_RE_REVIEW != 0 {
+            flag_string.push_str(&format!(" {}", "RE-REVIEW".magenta()));
+        }
+        if pr.is_draft {
+            flag_string.push_str(&format!(" {}", "DRAFT".bright_black()));
+        }
+        if pr.meta.stale {
+            flag_string.push_str(&format!(
+                " {}",
+                "STALE (query failed in the last sync)".red()
+            ));
+        }
+        if let Some(departure) = departure {
+            flag_string.push_str(&format!(
+                " {}",
+                format!(
+                    "LEFT {} ago ({})",
+                    PrettyDuration::from_duration(Utc::now() - departure.departed_at),
+                    departure.reason
+                )
+                .magenta()
+            ));
+        }
 
         let now = Utc::now();
-        let details = vec![
-            pr.repository.name_with_owner.bright_red().to_string(),
+        let mut details = vec![
+            if pr.host() == DEFAULT_HOST {
+                pr.repository.name_with_owner.bright_red().to_string()
+            } else {
+                format!("{}/{}", pr.host(), pr.repository.name_with_owner)
+                    .bright_red()
+                    .to_string()
+            },
             format!("(#{}) {}", pr.number, pr.title).cyan().to_string(),
             String::new(),
             flag_string,
-            format!("{} ({})", pr.author.login, pr.meta.label)
-                .yellow()
-                .to_string(),
+            format!(
+                "{} ({}), {}",
+                pr.author.login,
+                pr.meta.label,
+                get_waiting_on(pr)
+            )
+            .yellow()
+            .to_string(),
             format!(
                 "Created {}, updated {} ago",
                 PrettyDuration::from_duration(now - pr.created_at),
@@ -235,35 +474,299 @@ pub fn print_show_pull_request<W: Write>(
             )
             .yellow()
             .to_string(),
-            format!("{} comment(s)", pr.comments_count)
-                .yellow()
+            if pr.review_comments_count > 0 {
+                format!(
+                    "{} comment(s), {} review comment(s)",
+                    pr.comments_count, pr.review_comments_count
+                )
+            } else {
+                format!("{} comment(s)", pr.comments_count)
+            }
+            .yellow()
+            .to_string(),
+        ];
+        for (name, values) in [
+            ("Labels", pr.labels.join(", ")),
+            ("Assignees", pr.assignees.join(", ")),
+            ("Milestone", pr.milestone.clone()),
+        ] {
+            if !values.is_empty() {
+                details.push(format!("{}: {}", name, values).yellow().to_string());
+            }
+        }
+        if pr.kind == ItemKind::PullRequest {
+            details.extend(pull_request_details(pr));
+            details.extend(stack_details(pr, prs));
+        }
+        details.extend(link_details(pr, link_templates));
+        details.extend(timeline_details(pr, &pr_state));
+        details.extend([note, String::new(), pr.body.clone()]);
+
+        for detail in details {
+            writeln!(writer, "{}", detail)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Issues closed by the PR and tickets referenced by it.
+fn link_details(pr: &PullRequest, link_templates: &[CompiledTemplate]) -> Vec<String> {
+    let mut details: Vec<String> = pr
+        .closing_issues
+        .iter()
+        .map(|issue| {
+            format!("Closes #{} {} {}", issue.number, issue.title, issue.url)
+                .blue()
+                .to_string()
+        })
+        .collect();
+    details.extend(
+        links::ticket_links(link_templates, pr)
+            .into_iter()
+            .map(|link| format!("{} {}", link.text, link.url).blue().to_string()),
+    );
+    details
+}
+
+/// Review, merge and CI state, which issues do not have.
+fn pull_request_details(pr: &PullRequest) -> Vec<String> {
+    let details = vec![
+        match &pr.meta.review_requested_from {
+            ReviewRequestedFrom::NotRequested => String::new(),
+            ReviewRequestedFrom::Directly => {
+                "Review requested from you directly".cyan().to_string()
+            }
+            ReviewRequestedFrom::Team(team) => format!("Review requested from you via {}", team)
+                .cyan()
                 .to_string(),
-            {
-                let approvals: Vec<&str> = pr
+        },
+        match pr.re_review_url() {
+            Some(url) => format!("New commits since your review: {}", url)
+                .magenta()
+                .to_string(),
+            None => String::new(),
+        },
+        format!(
+            "Size {}: +{} -{} in {} file(s)",
+            pr.size_bucket().unwrap_or_default(),
+            pr.additions,
+            pr.deletions,
+            pr.changed_files
+        )
+        .yellow()
+        .to_string(),
+        {
+            let by_reviewers = |text: &str, state: &str| -> String {
+                let reviewers: Vec<&str> = pr
                     .latest_reviews
                     .iter()
-                    .filter(|r| r.state == "APPROVED")
+                    .filter(|r| r.state == state)
                     .map(|r| r.author_login.as_str())
                     .collect();
-                if approvals.is_empty() {
-                    "Not approved".yellow().to_string()
+                if reviewers.is_empty() {
+                    text.to_string()
                 } else {
-                    format!("Approved by {}", approvals.join(", "))
-                        .bright_green()
+                    format!("{} by {}", text, reviewers.join(", "))
+                }
+            };
+            if pr.has_changes_requested() {
+                by_reviewers("Changes requested", "CHANGES_REQUESTED")
+                    .red()
+                    .to_string()
+            } else if pr.is_approved() {
+                by_reviewers("Approved", "APPROVED")
+                    .bright_green()
+                    .to_string()
+            } else {
+                "Not approved".yellow().to_string()
+            }
+        },
+        if pr.is_conflicting() {
+            "Conflicts with the base branch".red().to_string()
+        } else if pr.is_blocked() {
+            "Merging is blocked".yellow().to_string()
+        } else if pr.merge_state_status == "CLEAN" {
+            "Ready to merge".bright_green().to_string()
+        } else if pr.merge_state_status == "BEHIND" {
+            "Behind the base branch".yellow().to_string()
+        } else {
+            format!("Merge state: {}", pr.merge_state_status.to_lowercase())
+                .yellow()
+                .to_string()
+        },
+        match pr.unresolved_threads {
+            0 => String::new(),
+            n => format!("{} unresolved thread(s)", n).yellow().to_string(),
+        },
+        {
+            let checks = &pr.checks;
+            if checks.is_failing() {
+                if checks.failing.is_empty() {
+                    "CI failing".red().to_string()
+                } else {
+                    format!("CI failing: {}", checks.failing.join(", "))
+                        .red()
                         .to_string()
                 }
-            },
-            note,
-            String::new(),
-            pr.body.clone(),
-        ];
+            } else if checks.is_pending() {
+                "CI pending".yellow().to_string()
+            } else if checks.is_success() {
+                "CI passed".bright_green().to_string()
+            } else {
+                "No CI checks".yellow().to_string()
+            }
+        },
+    ];
+    details.into_iter().filter(|d| !d.is_empty()).collect()
+}
 
-        for detail in details {
-            writeln!(writer, "{}", detail)?;
+/// Position of the PR in its stack, with the PRs it is stacked on and the PRs stacked on it.
+fn stack_details(pr: &PullRequest, prs: &[PullRequest]) -> Vec<String> {
+    let ancestors = stack::ancestors(pr, prs);
+    let children = stack::children(pr, prs);
+    if ancestors.is_empty() && children.is_empty() {
+        return Vec::new();
+    }
+    let describe = |p: &PullRequest| format!("#{} {}", p.number, p.title);
+    let mut lines = vec![format!(
+        "Stacked PR {} of {}",
+        ancestors.len() + 1,
+        stack::stack_size(pr, prs)
+    )
+    .cyan()
+    .to_string()];
+    match ancestors.last() {
+        Some(parent) => lines.push(format!("  Parent: {}", describe(parent))),
+        None => lines.push(format!("  Base: {}", pr.base_ref_name)),
+    }
+    for child in children {
+        lines.push(format!("  Child: {}", describe(child)));
+    }
+    lines
+}
+
+/// Compact log of the timeline events since the PR was last opened, or of all the recent events if it never was.
+fn timeline_details(pr: &PullRequest, pr_state: &PrState) -> Vec<String> {
+    let since = pr_state.opened_at;
+    let events: Vec<&TimelineEvent> = pr
+        .timeline
+        .iter()
+        .filter(|e| since.is_none_or(|since| e.created_at > since))
+        .collect();
+    // The commits are dated by their commit date, which is older than the push after a rebase, so the head is
+    // compared too.
+    let head_moved = since.is_some()
+        && !pr_state.last_head_oid.is_empty()
+        && !pr.head_ref_oid.is_empty()
+        && pr_state.last_head_oid != pr.head_ref_oid;
+    if events.is_empty() && !head_moved {
+        return Vec::new();
+    }
+
+    let of_kind = |kinds: &[TimelineEventKind]| -> Vec<&TimelineEvent> {
+        events
+            .iter()
+            .filter(|e| kinds.contains(&e.kind))
+            .copied()
+            .collect()
+    };
+    let changes = |added: TimelineEventKind, removed: TimelineEventKind| -> String {
+        of_kind(&[added, removed])
+            .iter()
+            .map(|e| {
+                let sign = if e.kind == added { "+" } else { "-" };
+                format!("{}{}", sign, e.detail)
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let header = if since.is_some() {
+        "Since last opened:"
+    } else {
+        "Recent activity:"
+    };
+    let mut lines = vec![String::new(), header.bright_white().to_string()];
+    let commits = of_kind(&[TimelineEventKind::Commit]);
+    if !commits.is_empty() {
+        lines.push(format!(
+            "  {} commit(s) by {}",
+            commits.len(),
+            count_by_actor(&commits)
+        ));
+    } else if head_moved {
+        lines.push("  New commits".to_string());
+    }
+    let pushes = of_kind(&[TimelineEventKind::ForcePushed]);
+    if !pushes.is_empty() {
+        lines.push(format!("  Force-pushed by {}", count_by_actor(&pushes)));
+    }
+    if head_moved {
+        lines.push(format!(
+            "  Changes: {}/files/{}..{}",
+            pr.url, pr_state.last_head_oid, pr.head_ref_oid
+        ));
+    }
+    let comments = of_kind(&[TimelineEventKind::Comment]);
+    if !comments.is_empty() {
+        lines.push(format!("  Comments by {}", count_by_actor(&comments)));
+    }
+    let review_comments = of_kind(&[TimelineEventKind::ReviewComment]);
+    if !review_comments.is_empty() {
+        lines.push(format!(
+            "  Review comments by {}",
+            count_by_actor(&review_comments)
+        ));
+    }
+    let reviews = of_kind(&[TimelineEventKind::Review]);
+    if !reviews.is_empty() {
+        let reviews: Vec<String> = reviews
+            .iter()
+            .map(|e| format!("{} {}", e.actor, e.detail.to_lowercase().replace('_', " ")))
+            .collect();
+        lines.push(format!("  Reviews: {}", reviews.join(", ")));
+    }
+    let labels = changes(TimelineEventKind::Labeled, TimelineEventKind::Unlabeled);
+    if !labels.is_empty() {
+        lines.push(format!("  Labels: {}", labels));
+    }
+    let reviewers = changes(
+        TimelineEventKind::ReviewRequested,
+        TimelineEventKind::ReviewRequestRemoved,
+    );
+    if !reviewers.is_empty() {
+        lines.push(format!("  Review requests: {}", reviewers));
+    }
+    lines
+}
+
+/// Actors of the events in order of appearance, with the number of events if more than one, e.g. "bob (2), alice".
+fn count_by_actor(events: &[&TimelineEvent]) -> String {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for event in events {
+        match counts.iter_mut().find(|(actor, _)| *actor == event.actor) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((&event.actor, 1)),
         }
     }
+    counts
+        .into_iter()
+        .map(|(actor, count)| match count {
+            1 => actor.to_string(),
+            n => format!("{} ({})", actor, n),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
 
-    Ok(())
+/// Push a single-character flag, colored unless the PR is muted, or a placeholder if there is no flag.
+fn push_flag(flag_string: &mut String, flag: Option<(&str, Color)>, mute: bool) {
+    match flag {
+        Some((flag, _)) if mute => flag_string.push_str(flag),
+        Some((flag, color)) => flag_string.push_str(&flag.color(color).to_string()),
+        None => flag_string.push_str(NBSP),
+    }
 }
 
 fn join_strings_cap_width(width: usize, left: &str, right: &str) -> String {
@@ -30,6 +30,9 @@ pub struct ReviewRequest {
     pub name: String,
     #[serde(default)]
     pub slug: String,
+    /// "org/slug" of a team.
+    #[serde(rename = "combinedSlug", default)]
+    pub combined_slug: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,14 +41,35 @@ pub struct Review {
     pub author_login: String,
     #[serde(default)]
     pub state: String,
+    /// The commit the review was made against.
+    #[serde(default)]
+    pub commit_oid: String,
 }
 
+/// An item tracked in the inbox, a pull request or an issue, told apart by `kind`. The type keeps its name from before
+/// issues were tracked. Issues leave the fields specific to pull requests (reviews, checks, merge state, branches,
+/// diff size) empty.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PullRequest {
+    #[serde(default)]
+    pub kind: ItemKind,
     pub author: Author,
     pub body: String,
+    /// Comments in the conversation, without the review comments.
     #[serde(rename = "commentsCount")]
     pub comments_count: i32,
+    /// Comments in the review threads.
+    #[serde(rename = "reviewCommentsCount", default)]
+    pub review_comments_count: i32,
+    #[serde(rename = "unresolvedThreads", default)]
+    pub unresolved_threads: i32,
+    /// Review threads, resolved or not.
+    #[serde(rename = "reviewThreadsCount", default)]
+    pub review_threads_count: i32,
+    /// The review threads were fetched, so `review_comments_count`, `unresolved_threads` and the review comments in the
+    /// timeline are known. False for the PRs stored before the review threads were tracked.
+    #[serde(rename = "reviewThreadsFetched", default)]
+    pub review_threads_fetched: bool,
     #[serde(rename = "createdAt")]
     pub created_at: DateTime<Utc>,
     pub id: String,
@@ -60,14 +84,272 @@ pub struct PullRequest {
     pub review_requests: Vec<ReviewRequest>,
     #[serde(rename = "latestReviews", default)]
     pub latest_reviews: Vec<Review>,
+    #[serde(default)]
+    pub checks: Checks,
+    #[serde(rename = "isDraft", default)]
+    pub is_draft: bool,
+    /// APPROVED, CHANGES_REQUESTED or REVIEW_REQUIRED. Empty if the repository does not require reviews.
+    #[serde(rename = "reviewDecision", default)]
+    pub review_decision: String,
+    /// MERGEABLE, CONFLICTING or UNKNOWN.
+    #[serde(default)]
+    pub mergeable: String,
+    /// E.g. CLEAN, BEHIND, BLOCKED, DIRTY or UNSTABLE.
+    #[serde(rename = "mergeStateStatus", default)]
+    pub merge_state_status: String,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    /// Logins of the assignees.
+    #[serde(default)]
+    pub assignees: Vec<String>,
+    /// Title of the milestone, empty if there is none.
+    #[serde(default)]
+    pub milestone: String,
+    /// Lines added by the PR. Zero for issues, like `deletions` and `changed_files`.
+    #[serde(default)]
+    pub additions: i32,
+    #[serde(default)]
+    pub deletions: i32,
+    #[serde(rename = "changedFiles", default)]
+    pub changed_files: i32,
+    /// Issues closed when the PR is merged, the first 3.
+    #[serde(rename = "closingIssues", default)]
+    pub closing_issues: Vec<LinkedIssue>,
+    /// Branch the PR is merged into. Empty for issues.
+    #[serde(rename = "baseRefName", default)]
+    pub base_ref_name: String,
+    /// Branch of the PR. Empty for issues.
+    #[serde(rename = "headRefName", default)]
+    pub head_ref_name: String,
+    /// The head commit of the PR. Empty for issues.
+    #[serde(rename = "headRefOid", default)]
+    pub head_ref_oid: String,
+    /// The head branch is in a fork, not in the repository of the PR.
+    #[serde(rename = "isCrossRepository", default)]
+    pub is_cross_repository: bool,
+    /// The 25 most recent events and the 10 most recent comments of each review thread, oldest first.
+    #[serde(default)]
+    pub timeline: Vec<TimelineEvent>,
     #[serde(rename = "_meta", default)]
     pub meta: Meta,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkedIssue {
+    pub number: i32,
+    pub title: String,
+    pub url: String,
+}
+
+/// Named after the GraphQL `__typename`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum ItemKind {
+    #[default]
+    PullRequest,
+    Issue,
+}
+
+impl PullRequest {
+    /// Host of the PR taken from its URL, e.g. "github.com". The URL identifies the PR across hosts.
+    pub fn host(&self) -> &str {
+        self.url.split('/').nth(2).unwrap_or_default()
+    }
+
+    /// Use the review decision of GitHub, or the latest reviews if the repository does not require reviews.
+    pub fn is_approved(&self) -> bool {
+        match self.review_decision.as_str() {
+            "" => {
+                !self.has_changes_requested()
+                    && self.latest_reviews.iter().any(|r| r.state == "APPROVED")
+            }
+            decision => decision == "APPROVED",
+        }
+    }
+
+    pub fn has_changes_requested(&self) -> bool {
+        match self.review_decision.as_str() {
+            "" => self
+                .latest_reviews
+                .iter()
+                .any(|r| r.state == "CHANGES_REQUESTED"),
+            decision => decision == "CHANGES_REQUESTED",
+        }
+    }
+
+    /// Comments in the conversation and in the review threads.
+    pub fn all_comments_count(&self) -> i32 {
+        self.comments_count + self.review_comments_count
+    }
+
+    /// Size of the PR by the lines changed: XS, S, M, L or XL. None for issues.
+    pub fn size_bucket(&self) -> Option<&'static str> {
+        if self.kind != ItemKind::PullRequest {
+            return None;
+        }
+        let size = match self.additions + self.deletions {
+            0..10 => "XS",
+            10..30 => "S",
+            30..100 => "M",
+            100..500 => "L",
+            _ => "XL",
+        };
+        Some(size)
+    }
+
+    /// The latest review of the viewer, if any.
+    pub fn viewer_review(&self) -> Option<&Review> {
+        if self.meta.viewer.is_empty() {
+            return None;
+        }
+        self.latest_reviews
+            .iter()
+            .find(|r| r.author_login == self.meta.viewer)
+    }
+
+    /// The viewer approved or requested changes, and commits were pushed since, so the review is stale.
+    pub fn needs_re_review(&self) -> bool {
+        self.viewer_review().is_some_and(|r| {
+            matches!(r.state.as_str(), "APPROVED" | "CHANGES_REQUESTED")
+                && !r.commit_oid.is_empty()
+                && !self.head_ref_oid.is_empty()
+                && r.commit_oid != self.head_ref_oid
+        })
+    }
+
+    /// Link to the changes between the commit of the viewer's review and the head of the PR.
+    pub fn re_review_url(&self) -> Option<String> {
+        if !self.needs_re_review() {
+            return None;
+        }
+        let review = self.viewer_review()?;
+        Some(format!(
+            "{}/files/{}..{}",
+            self.url, review.commit_oid, self.head_ref_oid
+        ))
+    }
+
+    /// Whether the viewer authored the PR.
+    pub fn is_own(&self) -> bool {
+        !self.meta.viewer.is_empty() && self.author.login == self.meta.viewer
+    }
+
+    pub fn is_conflicting(&self) -> bool {
+        self.mergeable == "CONFLICTING"
+    }
+
+    pub fn is_blocked(&self) -> bool {
+        self.merge_state_status == "BLOCKED"
+    }
+}
+
+/// An event in the timeline of a PR, used to tell what changed since the PR was last opened.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineEvent {
+    pub kind: TimelineEventKind,
+    /// Login of who did it, e.g. the commenter or the author of the commit.
+    #[serde(default)]
+    pub actor: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    /// The review state, the label or the requested reviewer. Empty for commits, comments and pushes.
+    #[serde(default)]
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimelineEventKind {
+    Commit,
+    Comment,
+    Review,
+    Labeled,
+    Unlabeled,
+    ReviewRequested,
+    ReviewRequestRemoved,
+    ReviewComment,
+    /// The branch was force-pushed, e.g. after a rebase. Its time is the time of the push, unlike for commits.
+    ForcePushed,
+}
+
+/// CI status of the head commit of a PR.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Checks {
+    /// The rollup state: SUCCESS, FAILURE, ERROR, PENDING or EXPECTED. Empty if there are no checks.
+    #[serde(default)]
+    pub state: String,
+    /// Names of the failing checks.
+    #[serde(default)]
+    pub failing: Vec<String>,
+}
+
+impl Checks {
+    pub fn is_success(&self) -> bool {
+        self.state == "SUCCESS"
+    }
+
+    pub fn is_failing(&self) -> bool {
+        self.state == "FAILURE" || self.state == "ERROR"
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.state == "PENDING" || self.state == "EXPECTED"
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Meta {
     #[serde(default)]
     pub label: String,
     #[serde(default)]
     pub default_mute: bool,
-}
\ No newline at end of file
+    /// Names of all the queries that returned the PR, before the dedup by `attribution_order` picked `label`.
+    #[serde(default)]
+    pub queries: Vec<String>,
+    /// The query of the PR failed in the last sync, and the PR is kept from an earlier sync.
+    #[serde(default)]
+    pub stale: bool,
+    /// Login of the user who synced the PR, on the host of the PR. Empty if unknown.
+    #[serde(default)]
+    pub viewer: String,
+    #[serde(default)]
+    pub review_requested_from: ReviewRequestedFrom,
+}
+
+/// How the viewer was asked to review a PR.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum ReviewRequestedFrom {
+    #[default]
+    NotRequested,
+    Directly,
+    /// Via a team of the viewer, as "org/slug".
+    Team(String),
+}
+
+/// API budget reported by GitHub.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimit {
+    pub cost: i32,
+    pub remaining: i32,
+    #[serde(rename = "resetAt")]
+    pub reset_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+impl PullRequest {
+    /// An open PR in "o/r" by `author`, without comments, reviews or events.
+    pub fn for_test(number: i32, author: &str) -> Self {
+        serde_json::from_value(serde_json::json!({
+            "author": { "login": author },
+            "body": "",
+            "commentsCount": 0,
+            "createdAt": "2026-10-01T00:00:00Z",
+            "id": format!("PR_{}", number),
+            "number": number,
+            "repository": { "name": "r", "nameWithOwner": "o/r" },
+            "title": format!("PR {}", number),
+            "updatedAt": "2026-10-02T00:00:00Z",
+            "url": format!("https://github.com/o/r/pull/{}", number),
+            "state": "OPEN",
+        }))
+        .unwrap()
+    }
+}
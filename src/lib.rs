@@ -2,7 +2,10 @@ pub mod actions;
 pub mod config;
 pub mod fzf;
 pub mod gh;
+pub mod links;
+pub mod stack;
 pub mod storage;
 pub mod sync;
+pub mod transport;
 pub mod util;
 pub mod xbar;
@@ -0,0 +1,139 @@
+//! Links to the tickets referenced by a PR, extracted with the link templates from the config.
+
+use crate::config::LinkTemplate;
+use crate::gh::PullRequest;
+use regex::Regex;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    /// The matched ticket key, e.g. "ABC-123".
+    pub text: String,
+    pub url: String,
+}
+
+/// A link template with its pattern compiled, see [compile].
+#[derive(Debug, Clone)]
+pub struct CompiledTemplate {
+    regex: Regex,
+    url: String,
+}
+
+/// Compile the link templates once for all the PRs. Templates with a problem are skipped, `config-check` reports them.
+pub fn compile(templates: &[LinkTemplate]) -> Vec<CompiledTemplate> {
+    templates
+        .iter()
+        .filter_map(|template| match compile_template(template) {
+            Ok(compiled) => Some(compiled),
+            Err(e) => {
+                log::debug!("Skip link template {}", e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Links for the matches of the templates in the title, the body and the branch of the PR, in order of appearance and
+/// without duplicates.
+pub fn ticket_links(templates: &[CompiledTemplate], pr: &PullRequest) -> Vec<Link> {
+    let mut links: Vec<Link> = Vec::new();
+    for template in templates {
+        for text in [&pr.title, &pr.body, &pr.head_ref_name] {
+            for caps in template.regex.captures_iter(text) {
+                if caps[0].is_empty() {
+                    continue;
+                }
+                let mut url = String::new();
+                caps.expand(&template.url, &mut url);
+                let link = Link {
+                    text: caps[0].to_string(),
+                    url,
+                };
+                if !links.contains(&link) {
+                    links.push(link);
+                }
+            }
+        }
+    }
+    links
+}
+
+/// Problems with the link templates, for `config-check`.
+pub fn validate(templates: &[LinkTemplate]) -> Vec<String> {
+    templates
+        .iter()
+        .filter_map(|template| compile_template(template).err())
+        .collect()
+}
+
+fn compile_template(template: &LinkTemplate) -> Result<CompiledTemplate, String> {
+    let regex = Regex::new(&template.pattern)
+        .map_err(|e| format!("link template '{}': {}", template.pattern, e))?;
+    if regex.is_match("") {
+        return Err(format!(
+            "link template '{}': matches the empty string",
+            template.pattern
+        ));
+    }
+    if template.url.is_empty() {
+        return Err(format!("link template '{}': no url", template.pattern));
+    }
+    Ok(CompiledTemplate {
+        regex,
+        url: template.url.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(pattern: &str, url: &str) -> LinkTemplate {
+        LinkTemplate {
+            pattern: pattern.to_string(),
+            url: url.to_string(),
+        }
+    }
+
+    #[test]
+    fn links_in_order_of_appearance_without_duplicates() {
+        let mut pr = PullRequest::for_test(1, "bob");
+        pr.title = "ABC-12 fix".to_string();
+        pr.body = "See ABC-7 and ABC-12".to_string();
+        let templates = compile(&[template("ABC-([0-9]+)", "https://t/$1")]);
+        let links: Vec<String> = ticket_links(&templates, &pr)
+            .into_iter()
+            .map(|link| link.url)
+            .collect();
+        assert_eq!(links, vec!["https://t/12", "https://t/7"]);
+    }
+
+    #[test]
+    fn pattern_matching_the_empty_string_is_rejected() {
+        let templates = [template("[A-Z]*", "https://t/$0")];
+        assert_eq!(validate(&templates).len(), 1);
+        assert!(compile(&templates).is_empty());
+    }
+
+    #[test]
+    fn zero_length_matches_are_skipped() {
+        let mut pr = PullRequest::for_test(1, "bob");
+        pr.title = "ABC fix".to_string();
+        let templates = compile(&[template(r"\b(?:ABC)?", "https://t/$0")]);
+        assert_eq!(templates.len(), 1);
+        let links = ticket_links(&templates, &pr);
+        assert_eq!(
+            links,
+            vec![Link {
+                text: "ABC".to_string(),
+                url: "https://t/ABC".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn invalid_templates_are_reported_and_skipped() {
+        let templates = [template("ABC-(", "https://t/$0"), template("ABC", "")];
+        assert_eq!(validate(&templates).len(), 2);
+        assert!(compile(&templates).is_empty());
+    }
+}
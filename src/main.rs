@@ -3,8 +3,10 @@ use chrono::{Duration, Utc};
 use clap::{Parser, Subcommand};
 use colored::control;
 use ffgh::actions;
+use ffgh::transport::{GhCliTransport, HttpTransport, Transport, TRANSPORT_GH, TRANSPORT_HTTP};
 use ffgh::{
-    config::Config, fzf, storage::FileStorage, storage::Storage, sync::Synchronizer, util, xbar,
+    config::Config, fzf, links, storage::FileStorage, storage::Storage, sync::Synchronizer, util,
+    xbar,
 };
 use std::env;
 use std::fs;
@@ -60,6 +62,8 @@ enum Commands {
     CycleNote { url: String },
     #[command(name = "config-check")]
     ConfigCheck,
+    #[command(name = "show-sync-status")]
+    ShowSyncStatus,
 }
 
 #[tokio::main]
@@ -97,6 +101,7 @@ async fn main() -> Result<()> {
         log::debug!("Config file {} does not exist, using default", config_path);
         Config::default()
     };
+    let link_templates = links::compile(&config.link_templates);
 
     let mut storage = FileStorage::new();
     storage.prs_state_path = Path::new(&state_path)
@@ -107,18 +112,32 @@ async fn main() -> Result<()> {
         .join(&storage.user_state_path)
         .to_string_lossy()
         .to_string();
+    storage.sync_status_path = Path::new(&state_path)
+        .join(&storage.sync_status_path)
+        .to_string_lossy()
+        .to_string();
+    storage.departed_state_path = Path::new(&state_path)
+        .join(&storage.departed_state_path)
+        .to_string_lossy()
+        .to_string();
     log::debug!("prs_state_path {:?}", storage.prs_state_path);
     log::debug!("user_state_path {:?}", storage.user_state_path);
+    log::debug!("sync_status_path {:?}", storage.sync_status_path);
+    log::debug!("departed_state_path {:?}", storage.departed_state_path);
 
     match cli.command {
-        Commands::Sync { once } => {
-            let synchronizer = Synchronizer::new(storage);
-            if once {
-                synchronizer.run_once(&config).await?;
-            } else {
-                synchronizer.run_blocking(&config).await?;
+        Commands::Sync { once } => match config.transport.as_str() {
+            "" | TRANSPORT_GH => {
+                run_sync(Synchronizer::new(storage, GhCliTransport), &config, once).await?;
             }
-        }
+            TRANSPORT_HTTP => {
+                let transport = HttpTransport::from_config(&config.graphql_url, &config.token)?;
+                run_sync(Synchronizer::new(storage, transport), &config, once).await?;
+            }
+            other => {
+                return Err(anyhow::anyhow!("Unknown transport: {}", other));
+            }
+        },
         Commands::Fzf => {
             let terminal_width = env::var("TERMINAL_WIDTH")
                 .unwrap_or_else(|_| "120".to_string())
@@ -128,58 +147,90 @@ async fn main() -> Result<()> {
             let prs = storage.get_pull_requests()?;
             let user_state = storage.get_user_state()?;
 
-            let sync_str = if let Some(sync_time) = storage.get_sync_time() {
+            let sync_status = storage.get_sync_status()?;
+            let sync_str = if let Some(sync_time) = sync_status.last_success {
                 let duration = Utc::now() - sync_time;
                 format!("X synced {} ago", format_duration(duration))
             } else {
                 "X not synced".to_string()
             };
 
-            println!("{} | {}", sync_str, user_state.settings.view_mode);
+            let mut header = vec![sync_str, user_state.settings.view_mode.clone()];
+            let grace = Duration::minutes(OUT_OF_SYNC_PERIOD_MINUTES);
+            if let Some(reason) = sync_status.out_of_sync_reason(Utc::now(), grace) {
+                header.push(reason);
+            }
+            header.extend(sync_status.pauses(Utc::now()));
+            if !sync_status.query_errors.is_empty() {
+                let failed: Vec<&str> = sync_status
+                    .query_errors
+                    .keys()
+                    .map(|k| k.as_str())
+                    .collect();
+                header.push(format!("failed: {}", failed.join(", ")));
+            }
+            println!("{}", header.join(" | "));
 
-            let prs = prs;
-            fzf::print_pull_requests(
-                &mut io::stdout(),
-                terminal_width,
-                &prs,
-                &user_state,
-                &config,
-            )?;
+            if user_state.settings.view_mode == fzf::VIEW_MODE_DEPARTED {
+                let departed = storage.get_departed_pull_requests()?;
+                fzf::print_departed_pull_requests(&mut io::stdout(), terminal_width, &departed)?;
+            } else {
+                fzf::print_pull_requests(
+                    &mut io::stdout(),
+                    terminal_width,
+                    &prs,
+                    &user_state,
+                    &config,
+                )?;
+            }
         }
         Commands::ShowCompactSummary => {
-            let prs = storage.get_pull_requests()?;
-            let prs = actions::apply_actions(&config, &prs);
-            let user_state = storage.get_user_state()?;
-
-            let out_of_sync_time = Utc::now() - Duration::minutes(OUT_OF_SYNC_PERIOD_MINUTES);
+            let grace = Duration::minutes(OUT_OF_SYNC_PERIOD_MINUTES);
             let compact_format = match &config.compact_format {
                 s if s.is_empty() => xbar::DEFAULT_FORMAT,
                 s => s,
             };
-            if let Some(sync_time) = storage.get_sync_time() {
-                if sync_time < out_of_sync_time {
-                    print!("GH err!");
-                } else {
-                    xbar::print_compact_summary(
-                        &mut io::stdout(),
-                        &prs,
-                        &user_state,
-                        compact_format,
-                    )?;
-                }
+            let sync_status = storage.get_sync_status()?;
+            if let Some(reason) = sync_status.out_of_sync_reason(Utc::now(), grace) {
+                xbar::print_out_of_sync(&mut io::stdout(), &reason, &sync_status)?;
             } else {
-                print!("GH err!");
+                let prs = storage.get_pull_requests()?;
+                let prs = actions::apply_actions(&config, &prs);
+                let user_state = storage.get_user_state()?;
+                xbar::print_compact_summary(
+                    &mut io::stdout(),
+                    &prs,
+                    &user_state,
+                    compact_format,
+                    config.compact_include_drafts,
+                    &link_templates,
+                )?;
+                xbar::print_query_errors(&mut io::stdout(), &sync_status)?;
             }
         }
         Commands::ShowPr { url } => {
             let prs = storage.get_pull_requests()?;
+            let departed = storage.get_departed_pull_requests()?;
             let user_state = storage.get_user_state()?;
-            fzf::print_show_pull_request(&mut io::stdout(), &url, &prs, &user_state)?;
+            if url == fzf::BOTS_GROUP_KEY {
+                let prs = actions::apply_actions(&config, &prs);
+                fzf::print_bots_group(&mut io::stdout(), &prs, &user_state)?;
+            } else {
+                fzf::print_show_pull_request(
+                    &mut io::stdout(),
+                    &url,
+                    &prs,
+                    &departed,
+                    &user_state,
+                    &link_templates,
+                )?;
+            }
         }
         Commands::MarkOpen {
             url,
             exit_error_if_marked,
         } => {
+            check_pr_url(&url)?;
             let marked = storage.mark_url_as_opened(&url)?;
             if !marked && exit_error_if_marked {
                 return Err(anyhow::anyhow!(
@@ -189,9 +240,11 @@ async fn main() -> Result<()> {
             }
         }
         Commands::MarkMute { url } => {
+            check_pr_url(&url)?;
             storage.mark_url_as_muted(&url)?;
         }
         Commands::AddNote { url, note_file } => {
+            check_pr_url(&url)?;
             let note = fs::read_to_string(note_file)?.trim().to_string();
             storage.add_note(&url, &note)?;
         }
@@ -207,6 +260,7 @@ async fn main() -> Result<()> {
             storage.write_user_state(&user_state)?;
         }
         Commands::CycleNote { url } => {
+            check_pr_url(&url)?;
             if config.annotations.is_empty() {
                 return Err(anyhow::anyhow!("No annotations set in config"));
             }
@@ -225,19 +279,62 @@ async fn main() -> Result<()> {
             log::debug!("Cycling note from '{}' to '{}'", current_note, new_note);
             storage.add_note(&url, &new_note)?;
         }
-        Commands::ConfigCheck => match serde_yaml::to_string(&config) {
-            Ok(s) => {
-                println!("{s}")
+        Commands::ShowSyncStatus => {
+            let sync_status = storage.get_sync_status()?;
+            println!("{}", serde_json::to_string_pretty(&sync_status)?);
+        }
+        Commands::ConfigCheck => {
+            match serde_yaml::to_string(&config) {
+                Ok(s) => {
+                    println!("{s}")
+                }
+                Err(e) => {
+                    println!("FAILED! {e}")
+                }
             }
-            Err(e) => {
-                println!("FAILED! {e}")
+            for warning in config.warnings() {
+                println!("WARNING! {warning}");
             }
-        },
+            let problems = config.validate();
+            for problem in &problems {
+                println!("FAILED! {problem}");
+            }
+            if !problems.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Found {} problem(s) in the config",
+                    problems.len()
+                ));
+            }
+        }
     }
 
     Ok(())
 }
 
+async fn run_sync<S: Storage, T: Transport>(
+    synchronizer: Synchronizer<S, T>,
+    config: &Config,
+    once: bool,
+) -> Result<()> {
+    for problem in config.validate().into_iter().chain(config.warnings()) {
+        log::warn!("Config problem, see config-check: {}", problem);
+    }
+    if once {
+        synchronizer.run_once(config).await
+    } else {
+        synchronizer.run_blocking(config).await
+    }
+}
+
+/// The fzf lines of groups, e.g. the bots, carry a key in place of the URL, which must not get a state of its own.
+fn check_pr_url(url: &str) -> Result<()> {
+    if url.starts_with("https://") || url.starts_with("http://") {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("Not a PR URL: {}", url))
+    }
+}
+
 fn get_default_state_dir() -> Result<String> {
     let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
     Ok(home.join(".ffgh").to_string_lossy().to_string())
@@ -0,0 +1,148 @@
+//! Detects stacked PRs. A PR is stacked on another PR of the same repository if its base branch is the head branch of
+//! the other PR, and that head branch is in the repository itself rather than in a fork. Only the PRs in the inbox are
+//! considered.
+
+use crate::gh::PullRequest;
+use std::collections::HashSet;
+
+/// The PR that `pr` is stacked on.
+pub fn parent<'a>(pr: &PullRequest, prs: &'a [PullRequest]) -> Option<&'a PullRequest> {
+    if pr.base_ref_name.is_empty() {
+        return None;
+    }
+    prs.iter().find(|p| is_stacked_on(pr, p))
+}
+
+/// The PRs stacked on `pr`.
+pub fn children<'a>(pr: &PullRequest, prs: &'a [PullRequest]) -> Vec<&'a PullRequest> {
+    if pr.head_ref_name.is_empty() {
+        return Vec::new();
+    }
+    prs.iter().filter(|p| is_stacked_on(p, pr)).collect()
+}
+
+/// The PRs that `pr` is stacked on, from the bottom of the stack.
+pub fn ancestors<'a>(pr: &PullRequest, prs: &'a [PullRequest]) -> Vec<&'a PullRequest> {
+    let mut ancestors: Vec<&PullRequest> = Vec::new();
+    let mut current = parent(pr, prs);
+    while let Some(p) = current {
+        if p.url == pr.url || ancestors.iter().any(|a| a.url == p.url) {
+            break;
+        }
+        ancestors.push(p);
+        current = parent(p, prs);
+    }
+    ancestors.reverse();
+    ancestors
+}
+
+/// Number of PRs in the stack of `pr`, including `pr`. 1 if it is not stacked.
+pub fn stack_size(pr: &PullRequest, prs: &[PullRequest]) -> usize {
+    let root = ancestors(pr, prs).first().copied().unwrap_or(pr);
+    let mut visited = HashSet::new();
+    let mut out = Vec::new();
+    push_tree(root, prs, 0, &mut visited, &mut out);
+    out.len()
+}
+
+/// Order the PRs so that each PR is followed by the PRs stacked on it, keeping the order otherwise. Each PR comes
+/// with its depth in the stack, 0 if it is not stacked on another PR.
+pub fn order_as_tree(prs: Vec<PullRequest>) -> Vec<(PullRequest, usize)> {
+    let mut visited = HashSet::new();
+    let mut out = Vec::new();
+    for pr in &prs {
+        if parent(pr, &prs).is_none() {
+            push_tree(pr, &prs, 0, &mut visited, &mut out);
+        }
+    }
+    // The PRs in cycles have a parent each, they are listed flat.
+    for pr in &prs {
+        push_tree(pr, &prs, 0, &mut visited, &mut out);
+    }
+    out.into_iter()
+        .map(|(pr, depth)| (pr.clone(), depth))
+        .collect()
+}
+
+fn push_tree<'a>(
+    pr: &'a PullRequest,
+    prs: &'a [PullRequest],
+    depth: usize,
+    visited: &mut HashSet<&'a str>,
+    out: &mut Vec<(&'a PullRequest, usize)>,
+) {
+    if !visited.insert(&pr.url) {
+        return;
+    }
+    out.push((pr, depth));
+    for child in children(pr, prs) {
+        push_tree(child, prs, depth + 1, visited, out);
+    }
+}
+
+/// Whether the base branch of `child` is the head branch of `parent`. The branch of a fork, e.g. its `main`, has the
+/// name of a branch of the repository but is another branch.
+fn is_stacked_on(child: &PullRequest, parent: &PullRequest) -> bool {
+    child.url != parent.url
+        && !parent.is_cross_repository
+        && same_repository(child, parent)
+        && parent.head_ref_name == child.base_ref_name
+}
+
+fn same_repository(a: &PullRequest, b: &PullRequest) -> bool {
+    a.host() == b.host() && a.repository.name_with_owner == b.repository.name_with_owner
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pr(number: i32, base: &str, head: &str) -> PullRequest {
+        let mut pr = PullRequest::for_test(number, "bob");
+        pr.base_ref_name = base.to_string();
+        pr.head_ref_name = head.to_string();
+        pr
+    }
+
+    fn numbers(tree: &[(PullRequest, usize)]) -> Vec<(i32, usize)> {
+        tree.iter().map(|(pr, depth)| (pr.number, *depth)).collect()
+    }
+
+    #[test]
+    fn order_as_tree_puts_children_under_parents() {
+        let prs = vec![
+            pr(3, "b", "c"),
+            pr(1, "main", "a"),
+            pr(4, "main", "d"),
+            pr(2, "a", "b"),
+        ];
+        let tree = order_as_tree(prs);
+        assert_eq!(numbers(&tree), vec![(1, 0), (2, 1), (3, 2), (4, 0)]);
+    }
+
+    #[test]
+    fn order_as_tree_lists_cycles_flat() {
+        let prs = vec![pr(1, "b", "a"), pr(2, "a", "b"), pr(3, "main", "c")];
+        let tree = order_as_tree(prs);
+        assert_eq!(numbers(&tree), vec![(3, 0), (1, 0), (2, 1)]);
+    }
+
+    #[test]
+    fn fork_branch_is_not_a_parent() {
+        let mut fork = pr(1, "main", "main");
+        fork.is_cross_repository = true;
+        let prs = vec![fork, pr(2, "main", "feature"), pr(3, "main", "fix")];
+        assert!(parent(&prs[1], &prs).is_none());
+        assert!(children(&prs[0], &prs).is_empty());
+        assert_eq!(numbers(&order_as_tree(prs)), vec![(1, 0), (2, 0), (3, 0)]);
+    }
+
+    #[test]
+    fn other_repository_is_not_a_parent() {
+        let mut other = pr(1, "main", "a");
+        other.repository.name_with_owner = "o/other".to_string();
+        let prs = vec![other, pr(2, "a", "b")];
+        assert!(parent(&prs[1], &prs).is_none());
+        assert_eq!(stack_size(&prs[1], &prs), 1);
+    }
+}
@@ -1,8 +1,9 @@
-use crate::gh::PullRequest;
+use crate::gh::{ItemKind, PullRequest, RateLimit, ReviewRequestedFrom, TimelineEventKind};
 use anyhow::Result;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Local, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Display};
 use std::fs;
 use std::path::Path;
 
@@ -13,14 +14,25 @@ pub trait Storage {
     fn mark_url_as_muted(&self, url: &str) -> Result<()>;
     fn get_user_state(&self) -> Result<UserState>;
     fn write_user_state(&self, state: &UserState) -> Result<()>;
-    fn get_sync_time(&self) -> Option<DateTime<Utc>>;
     fn add_note(&self, url: &str, note: &str) -> Result<()>;
+    fn get_sync_status(&self) -> Result<SyncStatus>;
+    fn write_sync_status(&self, status: &SyncStatus) -> Result<()>;
+    fn get_departed_pull_requests(&self) -> Result<Vec<DepartedPullRequest>>;
+    fn write_departed_pull_requests(&self, departed: &[DepartedPullRequest]) -> Result<()>;
 }
 
 #[derive(Debug, Clone)]
 pub struct FileStorage {
     pub prs_state_path: String,
     pub user_state_path: String,
+    pub sync_status_path: String,
+    pub departed_state_path: String,
+}
+
+impl Default for FileStorage {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl FileStorage {
@@ -28,12 +40,16 @@ impl FileStorage {
         Self {
             prs_state_path: "gh_daemon_state.json".to_string(),
             user_state_path: "gh_user_state.json".to_string(),
+            sync_status_path: "gh_sync_status.json".to_string(),
+            departed_state_path: "gh_departed_state.json".to_string(),
         }
     }
 
     fn get_pr_for_url(&self, url: &str) -> Result<PullRequest> {
         let prs = self.get_pull_requests()?;
+        let departed = self.get_departed_pull_requests()?;
         prs.into_iter()
+            .chain(departed.into_iter().map(|d| d.pr))
             .find(|pr| pr.url == url)
             .ok_or_else(|| anyhow::anyhow!("No PR found with URL: {}", url))
     }
@@ -79,6 +95,8 @@ impl Storage for FileStorage {
         if pr_state.opened_at.is_some()
             && pr_state.opened_at == Some(pr.updated_at)
             && pr_state.last_comment_count == pr.comments_count
+            && pr_state.last_all_comments_count == all_comments_count(&pr)
+            && pr_state.last_head_oid == pr.head_ref_oid
         {
             log::debug!("PR state up to date, not marking it as opened");
             return Ok(false);
@@ -87,6 +105,8 @@ impl Storage for FileStorage {
         log::debug!("PR state changed so it's marked as opened");
         pr_state.opened_at = Some(pr.updated_at);
         pr_state.last_comment_count = pr.comments_count;
+        pr_state.last_all_comments_count = all_comments_count(&pr);
+        pr_state.last_head_oid = pr.head_ref_oid.clone();
         user_state.per_url.insert(url.to_string(), pr_state);
 
         self.write_user_state(&user_state)?;
@@ -120,13 +140,6 @@ impl Storage for FileStorage {
         self.write_at_once(&self.user_state_path, json.as_bytes())
     }
 
-    fn get_sync_time(&self) -> Option<DateTime<Utc>> {
-        fs::metadata(&self.prs_state_path)
-            .ok()
-            .and_then(|metadata| metadata.modified().ok())
-            .map(|time| DateTime::from(time))
-    }
-
     fn add_note(&self, url: &str, note: &str) -> Result<()> {
         log::debug!("Add note to URL {}: {}", url, note);
         let mut user_state = self.get_user_state()?;
@@ -137,9 +150,39 @@ impl Storage for FileStorage {
 
         self.write_user_state(&user_state)
     }
+
+    fn get_sync_status(&self) -> Result<SyncStatus> {
+        if !Path::new(&self.sync_status_path).exists() {
+            return Ok(SyncStatus::default());
+        }
+
+        let content = fs::read_to_string(&self.sync_status_path)?;
+        let status: SyncStatus = serde_json::from_str(&content)?;
+        Ok(status)
+    }
+
+    fn write_sync_status(&self, status: &SyncStatus) -> Result<()> {
+        let json = serde_json::to_string_pretty(status)?;
+        self.write_at_once(&self.sync_status_path, json.as_bytes())
+    }
+
+    fn get_departed_pull_requests(&self) -> Result<Vec<DepartedPullRequest>> {
+        if !Path::new(&self.departed_state_path).exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&self.departed_state_path)?;
+        let departed: Vec<DepartedPullRequest> = serde_json::from_str(&content)?;
+        Ok(departed)
+    }
+
+    fn write_departed_pull_requests(&self, departed: &[DepartedPullRequest]) -> Result<()> {
+        let json = serde_json::to_string_pretty(departed)?;
+        self.write_at_once(&self.departed_state_path, json.as_bytes())
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct UserState {
     #[serde(rename = "PerUrl", default)]
     pub per_url: HashMap<String, PrState>,
@@ -147,13 +190,105 @@ pub struct UserState {
     pub settings: UserSettings,
 }
 
-impl Default for UserState {
-    fn default() -> Self {
-        Self {
-            per_url: HashMap::new(),
-            settings: UserSettings::default(),
+/// Outcome of the syncs, written by the synchronizer.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SyncStatus {
+    #[serde(rename = "LastAttempt", default)]
+    pub last_attempt: Option<DateTime<Utc>>,
+    /// Start of the last sync that stored the PRs, possibly with some of the queries failed.
+    #[serde(rename = "LastSuccess", default)]
+    pub last_success: Option<DateTime<Utc>>,
+    /// Duration of the last attempt.
+    #[serde(rename = "DurationMs", default)]
+    pub duration_ms: i64,
+    /// Number of PRs returned by each query in the last attempt, by query name.
+    #[serde(rename = "QueryCounts", default)]
+    pub query_counts: BTreeMap<String, usize>,
+    /// Errors of the queries that failed in the last attempt, by query name.
+    #[serde(rename = "QueryErrors", default)]
+    pub query_errors: BTreeMap<String, String>,
+    /// Error of the last attempt if it failed as a whole. Empty if it succeeded.
+    #[serde(rename = "LastError", default)]
+    pub last_error: String,
+    /// API budget of each host after its last sync, by host name. The cost is the total cost of the queries of the
+    /// host in that sync.
+    #[serde(rename = "RateLimits", default)]
+    pub rate_limits: BTreeMap<String, RateLimit>,
+    /// When the running sync plans its next attempt. None if no sync is running in a loop.
+    #[serde(rename = "NextSyncAt", default)]
+    pub next_sync_at: Option<DateTime<Utc>>,
+    /// Hosts whose syncs are paused until their API budget resets, with the reset time, by host name.
+    #[serde(rename = "PausedUntil", default)]
+    pub paused_until: BTreeMap<String, DateTime<Utc>>,
+}
+
+impl SyncStatus {
+    /// Tell why the PRs are out of sync, or return None if the last successful sync is recent, or the next sync is
+    /// not overdue: a sync may wait longer than usual for a long query interval or an exhausted API budget. `grace`
+    /// is how late a sync may be before it counts as out of sync.
+    pub fn out_of_sync_reason(&self, now: DateTime<Utc>, grace: Duration) -> Option<String> {
+        match (self.last_attempt, self.last_success) {
+            (None, _) => Some("never synced, is sync running?".to_string()),
+            (_, Some(success)) if success >= now - grace => None,
+            _ if !self.last_error.is_empty() => Some(format!("sync failed: {}", self.last_error)),
+            _ if self.next_sync_at.is_some_and(|next| now < next + grace) => None,
+            (Some(attempt), _) if attempt < now - grace => {
+                Some("no recent sync, is sync running?".to_string())
+            }
+            _ => Some("no successful sync".to_string()),
         }
     }
+
+    /// The hosts whose syncs are paused, e.g. "github.com paused until 14:05 (API budget exhausted)".
+    pub fn pauses(&self, now: DateTime<Utc>) -> Vec<String> {
+        self.paused_until
+            .iter()
+            .filter(|(_, until)| **until > now)
+            .map(|(host, until)| {
+                format!(
+                    "{} paused until {} (API budget exhausted)",
+                    host,
+                    until.with_timezone(&Local).format("%H:%M")
+                )
+            })
+            .collect()
+    }
+}
+
+/// A PR that disappeared from the results of the queries, kept for a while to tell what happened to it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DepartedPullRequest {
+    #[serde(rename = "PullRequest")]
+    pub pr: PullRequest,
+    #[serde(rename = "Reason")]
+    pub reason: DepartureReason,
+    #[serde(rename = "DepartedAt")]
+    pub departed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DepartureReason {
+    Merged,
+    Closed,
+    /// Still open, but no longer matches the queries, e.g. the review request was removed.
+    Unmatched,
+    /// Deleted, or no longer accessible to the viewer.
+    Deleted,
+    /// The final state could not be looked up.
+    Unknown,
+}
+
+impl Display for DepartureReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            DepartureReason::Merged => "merged",
+            DepartureReason::Closed => "closed",
+            DepartureReason::Unmatched => "unmatched",
+            DepartureReason::Deleted => "deleted",
+            DepartureReason::Unknown => "unknown",
+        };
+        write!(f, "{}", s)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -178,33 +313,388 @@ fn default_view_mode() -> String {
 pub struct PrState {
     #[serde(rename = "OpenedAt")]
     pub opened_at: Option<DateTime<Utc>>,
+    /// Comments in the conversation when the PR was last opened.
     #[serde(rename = "LastCommentCount", default)]
     pub last_comment_count: i32,
+    /// Comments in the conversation and in the review threads when the PR was last opened. Missing in the state
+    /// written before the review comments were counted, then only the comments in the conversation are compared.
+    #[serde(rename = "LastAllCommentsCount", default)]
+    pub last_all_comments_count: Option<i32>,
+    /// The head commit of the PR when it was last opened, to tell new commits by the commit rather than by its date,
+    /// which can be older after a rebase. Empty for issues.
+    #[serde(rename = "LastHeadOid", default)]
+    pub last_head_oid: String,
     #[serde(rename = "Note", default)]
     pub note: String,
     #[serde(rename = "IsMute", default)]
     pub is_mute: bool,
 }
 
+/// The comments with the review comments, to store at the open. None if the review comments are not known yet, then
+/// only the comments in the conversation are compared, so the review comments do not all turn new once fetched.
+fn all_comments_count(pr: &PullRequest) -> Option<i32> {
+    pr.review_threads_fetched.then(|| pr.all_comments_count())
+}
+
 pub const HAS_NEW_COMMENTS: u8 = 1 << 0;
 pub const IS_UPDATED: u8 = 1 << 1;
 pub const IS_NEW: u8 = 1 << 2;
+pub const NEEDS_RE_REVIEW: u8 = 1 << 3;
 
+/// Flags of the PR compared with the snapshot taken when it was last opened. The activity of the viewer does not
+/// count: their own PRs are not new, their comments are not new comments, and the PR is not updated if all the
+/// events since it was opened are theirs. [NEEDS_RE_REVIEW] is set if commits were pushed after the viewer's review.
 pub fn get_pr_state_flags(pr: &PullRequest, pr_state: &PrState) -> u8 {
     let mut flags = 0;
 
-    if pr.comments_count > pr_state.last_comment_count {
-        flags |= HAS_NEW_COMMENTS;
+    let own_events_since = |kinds: &[TimelineEventKind]| -> Option<usize> {
+        if pr.meta.viewer.is_empty() {
+            return None;
+        }
+        let events: Vec<_> = pr
+            .timeline
+            .iter()
+            .filter(|e| pr_state.opened_at.is_none_or(|t| e.created_at > t))
+            .filter(|e| kinds.is_empty() || kinds.contains(&e.kind))
+            .collect();
+        let own = events.iter().filter(|e| e.actor == pr.meta.viewer).count();
+        (own == events.len()).then_some(own)
+    };
+
+    let new_comments = match pr_state.last_all_comments_count {
+        Some(count) => pr.all_comments_count() - count,
+        None => pr.comments_count - pr_state.last_comment_count,
+    };
+    if new_comments > 0 {
+        let own_comments =
+            own_events_since(&[TimelineEventKind::Comment, TimelineEventKind::ReviewComment])
+                .unwrap_or(0);
+        if new_comments as usize > own_comments {
+            flags |= HAS_NEW_COMMENTS;
+        }
     }
 
     if pr_state.opened_at.is_none() {
-        flags |= IS_NEW;
+        if !pr.is_own() {
+            flags |= IS_NEW;
+        }
     } else if let Some(opened_at) = pr_state.opened_at {
-        if pr.updated_at > opened_at {
+        let only_own_events = own_events_since(&[]).is_some_and(|own| own > 0);
+        if pr.updated_at > opened_at && !only_own_events {
             flags |= IS_UPDATED;
         }
     }
 
+    if pr.needs_re_review() {
+        flags |= NEEDS_RE_REVIEW;
+    }
+
     flags
 }
 
+/// Whose turn it is to act on a PR.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitingOn {
+    Me,
+    Author,
+    Others,
+}
+
+impl Display for WaitingOn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            WaitingOn::Me => "waiting on me",
+            WaitingOn::Author => "waiting on author",
+            WaitingOn::Others => "waiting on others",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Tell whose turn it is, from the review requests, the latest reviews, the authorship of the viewer and who acted
+/// last. Without the viewer login, the PR is waiting on others.
+pub fn get_waiting_on(pr: &PullRequest) -> WaitingOn {
+    let viewer = pr.meta.viewer.as_str();
+    if viewer.is_empty() {
+        return WaitingOn::Others;
+    }
+    let last_actor = pr
+        .timeline
+        .iter()
+        .rev()
+        .find(|e| !e.actor.is_empty())
+        .map(|e| e.actor.as_str());
+    let broken = pr.is_conflicting() || pr.checks.is_failing();
+
+    if pr.kind == ItemKind::Issue {
+        let involved = pr.is_own() || pr.assignees.iter().any(|a| a == viewer);
+        return match last_actor {
+            Some(actor) if involved && actor != viewer => WaitingOn::Me,
+            _ => WaitingOn::Others,
+        };
+    }
+
+    if pr.is_own() {
+        let others_acted_last = last_actor.is_some_and(|actor| actor != viewer);
+        let ready_to_merge = pr.is_approved() && pr.merge_state_status == "CLEAN";
+        return if pr.has_changes_requested() || broken || ready_to_merge || others_acted_last {
+            WaitingOn::Me
+        } else {
+            WaitingOn::Others
+        };
+    }
+
+    let requested = pr.meta.review_requested_from != ReviewRequestedFrom::NotRequested
+        || pr.review_requests.iter().any(|r| r.login == viewer);
+    if requested || pr.needs_re_review() {
+        return WaitingOn::Me;
+    }
+    let reviewed = pr.viewer_review().is_some();
+    let author_acted_last = last_actor == Some(pr.author.login.as_str());
+    if reviewed && author_acted_last {
+        return WaitingOn::Me;
+    }
+    if pr.has_changes_requested() || broken {
+        return WaitingOn::Author;
+    }
+    WaitingOn::Others
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gh::{Review, ReviewRequest, TimelineEvent};
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        format!("2026-10-03T{:02}:00:00Z", hour).parse().unwrap()
+    }
+
+    fn event(kind: TimelineEventKind, actor: &str, hour: u32) -> TimelineEvent {
+        TimelineEvent {
+            kind,
+            actor: actor.to_string(),
+            created_at: at(hour),
+            detail: String::new(),
+        }
+    }
+
+    fn opened_at(hour: u32, comments: i32, all_comments: Option<i32>) -> PrState {
+        PrState {
+            opened_at: Some(at(hour)),
+            last_comment_count: comments,
+            last_all_comments_count: all_comments,
+            ..Default::default()
+        }
+    }
+
+    fn review(author: &str, state: &str) -> Review {
+        Review {
+            author_login: author.to_string(),
+            state: state.to_string(),
+            commit_oid: "head".to_string(),
+        }
+    }
+
+    /// A PR by bob, seen by carol.
+    fn pr_seen_by_carol() -> PullRequest {
+        let mut pr = PullRequest::for_test(1, "bob");
+        pr.meta.viewer = "carol".to_string();
+        pr.head_ref_oid = "head".to_string();
+        pr
+    }
+
+    #[test]
+    fn unopened_pr_is_new_unless_own() {
+        let mut pr = PullRequest::for_test(1, "bob");
+        pr.meta.viewer = "carol".to_string();
+        assert_eq!(get_pr_state_flags(&pr, &PrState::default()), IS_NEW);
+
+        pr.meta.viewer = "bob".to_string();
+        assert_eq!(get_pr_state_flags(&pr, &PrState::default()), 0);
+    }
+
+    #[test]
+    fn review_comments_are_new_comments() {
+        let mut pr = PullRequest::for_test(1, "bob");
+        pr.updated_at = at(10);
+        pr.comments_count = 2;
+        pr.review_comments_count = 3;
+        pr.timeline = vec![event(TimelineEventKind::ReviewComment, "dave", 9)];
+        let flags = get_pr_state_flags(&pr, &opened_at(10, 2, Some(4)));
+        assert_eq!(flags, HAS_NEW_COMMENTS);
+    }
+
+    #[test]
+    fn review_comments_count_as_seen_in_state_without_all_comments_count() {
+        let mut pr = PullRequest::for_test(1, "bob");
+        pr.updated_at = at(10);
+        pr.comments_count = 2;
+        pr.review_comments_count = 3;
+        let state: PrState = serde_json::from_value(serde_json::json!({
+            "OpenedAt": at(10),
+            "LastCommentCount": 2,
+        }))
+        .unwrap();
+        assert_eq!(get_pr_state_flags(&pr, &state), 0);
+
+        pr.comments_count = 3;
+        assert_eq!(get_pr_state_flags(&pr, &state), HAS_NEW_COMMENTS);
+    }
+
+    #[test]
+    fn own_activity_is_not_new() {
+        let mut pr = PullRequest::for_test(1, "bob");
+        pr.meta.viewer = "carol".to_string();
+        pr.updated_at = at(12);
+        pr.comments_count = 1;
+        pr.timeline = vec![event(TimelineEventKind::Comment, "carol", 11)];
+        assert_eq!(get_pr_state_flags(&pr, &opened_at(10, 0, Some(0))), 0);
+
+        pr.comments_count = 2;
+        pr.timeline
+            .push(event(TimelineEventKind::Comment, "dave", 12));
+        let flags = get_pr_state_flags(&pr, &opened_at(10, 0, Some(0)));
+        assert_eq!(flags, HAS_NEW_COMMENTS | IS_UPDATED);
+    }
+
+    #[test]
+    fn without_viewer_waiting_on_others() {
+        let mut pr = pr_seen_by_carol();
+        pr.meta.viewer.clear();
+        pr.review_decision = "CHANGES_REQUESTED".to_string();
+        assert_eq!(get_waiting_on(&pr), WaitingOn::Others);
+    }
+
+    #[test]
+    fn requested_review_waits_on_me() {
+        let mut pr = pr_seen_by_carol();
+        assert_eq!(get_waiting_on(&pr), WaitingOn::Others);
+
+        pr.review_requests = vec![ReviewRequest {
+            login: "carol".to_string(),
+            name: String::new(),
+            slug: String::new(),
+            combined_slug: String::new(),
+        }];
+        assert_eq!(get_waiting_on(&pr), WaitingOn::Me);
+
+        pr.review_requests.clear();
+        pr.meta.review_requested_from = ReviewRequestedFrom::Team("o/core".to_string());
+        assert_eq!(get_waiting_on(&pr), WaitingOn::Me);
+    }
+
+    #[test]
+    fn stale_review_waits_on_me() {
+        let mut pr = pr_seen_by_carol();
+        pr.latest_reviews = vec![review("carol", "APPROVED")];
+        assert_eq!(get_waiting_on(&pr), WaitingOn::Others);
+
+        pr.head_ref_oid = "newer".to_string();
+        assert_eq!(get_waiting_on(&pr), WaitingOn::Me);
+    }
+
+    #[test]
+    fn author_reply_after_my_review_waits_on_me() {
+        let mut pr = pr_seen_by_carol();
+        pr.latest_reviews = vec![review("carol", "COMMENTED")];
+        pr.timeline = vec![event(TimelineEventKind::Review, "carol", 9)];
+        assert_eq!(get_waiting_on(&pr), WaitingOn::Others);
+
+        pr.timeline
+            .push(event(TimelineEventKind::Comment, "bob", 10));
+        assert_eq!(get_waiting_on(&pr), WaitingOn::Me);
+    }
+
+    #[test]
+    fn requested_changes_or_failing_checks_wait_on_author() {
+        let mut pr = pr_seen_by_carol();
+        pr.review_decision = "CHANGES_REQUESTED".to_string();
+        assert_eq!(get_waiting_on(&pr), WaitingOn::Author);
+
+        pr.review_decision.clear();
+        pr.checks.state = "FAILURE".to_string();
+        assert_eq!(get_waiting_on(&pr), WaitingOn::Author);
+    }
+
+    #[test]
+    fn own_pr_waits_on_me_when_it_needs_action() {
+        let mut pr = pr_seen_by_carol();
+        pr.author.login = "carol".to_string();
+        pr.timeline = vec![event(TimelineEventKind::Commit, "carol", 9)];
+        assert_eq!(get_waiting_on(&pr), WaitingOn::Others);
+
+        pr.timeline
+            .push(event(TimelineEventKind::Comment, "dave", 10));
+        assert_eq!(get_waiting_on(&pr), WaitingOn::Me);
+
+        pr.timeline.truncate(1);
+        pr.review_decision = "APPROVED".to_string();
+        pr.merge_state_status = "CLEAN".to_string();
+        assert_eq!(get_waiting_on(&pr), WaitingOn::Me);
+
+        pr.merge_state_status = "BLOCKED".to_string();
+        assert_eq!(get_waiting_on(&pr), WaitingOn::Others);
+    }
+
+    #[test]
+    fn issue_waits_on_me_when_assigned_and_others_acted_last() {
+        let mut issue = pr_seen_by_carol();
+        issue.kind = ItemKind::Issue;
+        issue.timeline = vec![event(TimelineEventKind::Comment, "dave", 9)];
+        assert_eq!(get_waiting_on(&issue), WaitingOn::Others);
+
+        issue.assignees = vec!["carol".to_string()];
+        assert_eq!(get_waiting_on(&issue), WaitingOn::Me);
+
+        issue
+            .timeline
+            .push(event(TimelineEventKind::Comment, "carol", 10));
+        assert_eq!(get_waiting_on(&issue), WaitingOn::Others);
+    }
+
+    fn minutes_ago(minutes: i64) -> Option<DateTime<Utc>> {
+        Some(Utc::now() - Duration::minutes(minutes))
+    }
+
+    #[test]
+    fn waiting_for_the_next_sync_is_not_out_of_sync() {
+        let grace = Duration::minutes(5);
+        let mut status = SyncStatus {
+            last_attempt: minutes_ago(20),
+            last_success: minutes_ago(20),
+            ..Default::default()
+        };
+        assert_eq!(
+            status.out_of_sync_reason(Utc::now(), grace).as_deref(),
+            Some("no recent sync, is sync running?")
+        );
+
+        // Paused until the API budget resets, or a long query interval.
+        status.next_sync_at = minutes_ago(-10);
+        assert_eq!(status.out_of_sync_reason(Utc::now(), grace), None);
+
+        status.next_sync_at = minutes_ago(10);
+        assert!(status.out_of_sync_reason(Utc::now(), grace).is_some());
+
+        status.next_sync_at = minutes_ago(-10);
+        status.last_error = "HTTP 401".to_string();
+        assert_eq!(
+            status.out_of_sync_reason(Utc::now(), grace).as_deref(),
+            Some("sync failed: HTTP 401")
+        );
+    }
+
+    #[test]
+    fn only_current_pauses_are_shown() {
+        let status = SyncStatus {
+            paused_until: BTreeMap::from([
+                ("github.com".to_string(), minutes_ago(-10).unwrap()),
+                ("github.example.com".to_string(), minutes_ago(10).unwrap()),
+            ]),
+            ..Default::default()
+        };
+        let pauses = status.pauses(Utc::now());
+        assert_eq!(pauses.len(), 1);
+        assert!(pauses[0].starts_with("github.com paused until "));
+    }
+}
@@ -1,20 +1,75 @@
-use crate::config::Config;
-use crate::gh::{Author, Meta, PullRequest, Repository, Review, ReviewRequest};
-use crate::storage::Storage;
+use crate::config::{
+    Config, Query, DRAFTS_EXCLUDE, DRAFTS_INCLUDE, DRAFTS_ONLY, KIND_ISSUE, KIND_PR,
+    REVIEW_REQUESTED_DIRECT, REVIEW_REQUESTED_TEAM,
+};
+use crate::gh::{
+    Author, Checks, ItemKind, LinkedIssue, Meta, PullRequest, RateLimit, Repository, Review,
+    ReviewRequest, ReviewRequestedFrom, TimelineEvent, TimelineEventKind,
+};
+use crate::storage::{DepartedPullRequest, DepartureReason, Storage, SyncStatus};
+use crate::transport::{self, ErrorKind, Host, Transport, TransportError};
 use anyhow::Result;
 use chrono::{DateTime, Utc};
+use futures::stream::{self, StreamExt};
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
-use std::collections::HashMap;
-use std::process::Command;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
 use std::time::Duration;
 use tokio::time;
 
+/// Page size of GitHub connections, which is also the maximum GitHub allows.
+const PAGE_SIZE: usize = 100;
+/// GitHub search never returns more than 1000 results.
+const DEFAULT_MAX_RESULTS: usize = 1000;
+/// Upper bound of pages fetched for connections nested in a PR (review requests, reviews, review threads).
+const MAX_NESTED_PAGES: usize = 10;
+/// PRs whose review threads are fetched in one request.
+const REVIEW_THREADS_BATCH_SIZE: usize = 20;
+const DEFAULT_CONCURRENCY: usize = 4;
+const DEFAULT_QUERY_TIMEOUT: Duration = Duration::from_secs(60);
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(5);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(600);
+const DEFAULT_SYNC_INTERVAL: Duration = Duration::from_secs(60);
+/// The shortest sleep between two syncs, so that the loop never spins.
+const MIN_SYNC_DELAY: Duration = Duration::from_secs(5);
+/// How long the PRs that left the results are kept in the departed list.
+const DEPARTED_RETENTION: chrono::Duration = chrono::Duration::days(7);
+/// When the remaining API budget is less than this many syncs, pause until the budget resets.
+const RATE_LIMIT_RESERVE_SYNCS: i32 = 2;
+
 const GRAPHQL_QUERY: &str = r#"
-query($q: String!) {
-  search(query: $q, type: ISSUE, first: 100) {
+query($q: String!, $first: Int!, $after: String) {
+  rateLimit { cost remaining resetAt }
+  viewer { login }
+  search(query: $q, type: ISSUE, first: $first, after: $after) {
+    issueCount
+    pageInfo { hasNextPage endCursor }
     nodes {
+      ... on Issue {
+        __typename
+        author { __typename login url ... on Node { id } }
+        body
+        comments { totalCount }
+        createdAt
+        id
+        number
+        repository { name nameWithOwner }
+        title
+        updatedAt
+        url
+        state
+        labels(first: 20) { nodes { name } }
+        assignees(first: 20) { nodes { login } }
+        milestone { title }
+        timelineItems(last: 25, itemTypes: [ISSUE_COMMENT, LABELED_EVENT, UNLABELED_EVENT]) {
+          nodes { __typename ...TimelineComment ...TimelineLabeled ...TimelineUnlabeled }
+        }
+      }
       ... on PullRequest {
-        author { login }
+        __typename
+        author { __typename login url ... on Node { id } }
         body
         comments { totalCount }
         createdAt
@@ -25,66 +80,534 @@ query($q: String!) {
         updatedAt
         url
         state
+        labels(first: 20) { nodes { name } }
+        assignees(first: 20) { nodes { login } }
+        milestone { title }
+        additions
+        deletions
+        changedFiles
+        closingIssuesReferences(first: 3) { nodes { number title url } }
+        isDraft
+        baseRefName
+        headRefName
+        headRefOid
+        isCrossRepository
+        reviewDecision
+        mergeable
+        mergeStateStatus
         reviewRequests(first: 100) {
+          pageInfo { hasNextPage endCursor }
           nodes {
             requestedReviewer {
               ... on User { login }
-              ... on Team { name slug }
+              ... on Team { name slug combinedSlug }
             }
           }
         }
         latestReviews(first: 100) {
+          pageInfo { hasNextPage endCursor }
           nodes {
             author { login }
             state
+            commit { oid }
           }
         }
+        reviewThreads { totalCount }
+        commits(last: 1) {
+          nodes {
+            commit {
+              statusCheckRollup {
+                state
+                contexts(first: 100) {
+                  nodes {
+                    ... on CheckRun { name conclusion }
+                    ... on StatusContext { context state }
+                  }
+                }
+              }
+            }
+          }
+        }
+        timelineItems(
+          last: 25
+          itemTypes: [
+            PULL_REQUEST_COMMIT
+            ISSUE_COMMENT
+            PULL_REQUEST_REVIEW
+            LABELED_EVENT
+            UNLABELED_EVENT
+            REVIEW_REQUESTED_EVENT
+            REVIEW_REQUEST_REMOVED_EVENT
+            HEAD_REF_FORCE_PUSHED_EVENT
+          ]
+        ) {
+          nodes {
+            __typename
+            ...TimelineComment
+            ...TimelineLabeled
+            ...TimelineUnlabeled
+            ... on PullRequestCommit { commit { committedDate author { name user { login } } } }
+            ... on PullRequestReview { author { login } createdAt state }
+            ... on ReviewRequestedEvent {
+              actor { login }
+              createdAt
+              requestedReviewer { ... on User { login } ... on Team { name slug } }
+            }
+            ... on ReviewRequestRemovedEvent {
+              actor { login }
+              createdAt
+              requestedReviewer { ... on User { login } ... on Team { name slug } }
+            }
+            ... on HeadRefForcePushedEvent { actor { login } createdAt }
+          }
+        }
+      }
+    }
+  }
+}
+
+fragment TimelineComment on IssueComment { author { login } createdAt }
+fragment TimelineLabeled on LabeledEvent { actor { login } createdAt label { name } }
+fragment TimelineUnlabeled on UnlabeledEvent { actor { login } createdAt label { name } }
+"#;
+
+const FINAL_STATE_QUERY: &str = r#"
+query($ids: [ID!]!) {
+  nodes(ids: $ids) {
+    ... on PullRequest { state }
+    ... on Issue { state }
+  }
+}
+"#;
+
+const REVIEW_REQUESTS_QUERY: &str = r#"
+query($id: ID!, $after: String) {
+  rateLimit { cost remaining resetAt }
+  node(id: $id) {
+    ... on PullRequest {
+      reviewRequests(first: 100, after: $after) {
+        pageInfo { hasNextPage endCursor }
+        nodes {
+          requestedReviewer {
+            ... on User { login }
+            ... on Team { name slug combinedSlug }
+          }
+        }
+      }
+    }
+  }
+}
+"#;
+
+/// The threads with their comments are too costly to nest into the search, so they are fetched only for the PRs that
+/// changed since the last sync and have threads, [REVIEW_THREADS_BATCH_SIZE] PRs at a time.
+const REVIEW_THREADS_BATCH_QUERY: &str = r#"
+query($ids: [ID!]!) {
+  rateLimit { cost remaining resetAt }
+  nodes(ids: $ids) {
+    ... on PullRequest {
+      reviewThreads(first: 100) {
+        pageInfo { hasNextPage endCursor }
+        nodes {
+          isResolved
+          comments(last: 10) { totalCount nodes { author { login } createdAt } }
+        }
+      }
+    }
+  }
+}
+"#;
+
+/// The review threads of a PR past the first page of [REVIEW_THREADS_BATCH_QUERY].
+const REVIEW_THREADS_QUERY: &str = r#"
+query($id: ID!, $after: String) {
+  rateLimit { cost remaining resetAt }
+  node(id: $id) {
+    ... on PullRequest {
+      reviewThreads(first: 100, after: $after) {
+        pageInfo { hasNextPage endCursor }
+        nodes {
+          isResolved
+          comments(last: 10) { totalCount nodes { author { login } createdAt } }
+        }
+      }
+    }
+  }
+}
+"#;
+
+const VIEWER_TEAMS_QUERY: &str = r#"
+query($login: String!) {
+  rateLimit { cost remaining resetAt }
+  viewer {
+    organizations(first: 100) {
+      nodes {
+        teams(first: 100, userLogins: [$login]) { nodes { combinedSlug } }
+      }
+    }
+  }
+}
+"#;
+
+const LATEST_REVIEWS_QUERY: &str = r#"
+query($id: ID!, $after: String) {
+  rateLimit { cost remaining resetAt }
+  node(id: $id) {
+    ... on PullRequest {
+      latestReviews(first: 100, after: $after) {
+        pageInfo { hasNextPage endCursor }
+        nodes {
+          author { login }
+          state
+          commit { oid }
+        }
       }
     }
   }
 }
 "#;
 
-pub struct Synchronizer<S: Storage> {
+pub struct Synchronizer<S: Storage, T: Transport> {
     storage: S,
-    interval: Duration,
+    transport: T,
+    /// The last successful result of each query, by query name.
+    query_states: Mutex<HashMap<String, QueryState>>,
+    /// The queries that failed since their last success, by query name.
+    query_failures: Mutex<HashMap<String, QueryFailure>>,
+    /// The earliest time of the next sync of each host whose API budget runs low, by host name.
+    held_until: Mutex<HashMap<String, DateTime<Utc>>>,
+    /// Teams of the viewer as "org/slug", by host. Resolved on first use, and looked up again only after a failure.
+    viewer_teams: Mutex<HashMap<Host, TeamsLookup>>,
+}
+
+/// PRs found by a single query, with the API budget reported by GitHub.
+struct QueryResult {
+    prs: Vec<PullRequest>,
+    rate_limit: Option<RateLimit>,
 }
 
-impl<S: Storage> Synchronizer<S> {
-    pub fn new(storage: S) -> Self {
+struct QueryState {
+    synced_at: DateTime<Utc>,
+    prs: Vec<PullRequest>,
+}
+
+enum TeamsLookup {
+    Resolved(Vec<String>),
+    /// The lookup failed, and is not retried before `failure.retry_at`.
+    Failed {
+        failure: QueryFailure,
+        error: String,
+    },
+}
+
+/// Consecutive failures of a query, and when to retry it.
+struct QueryFailure {
+    count: u32,
+    /// Kind of the last failure.
+    kind: ErrorKind,
+    retry_at: DateTime<Utc>,
+}
+
+impl<S: Storage, T: Transport> Synchronizer<S, T> {
+    pub fn new(storage: S, transport: T) -> Self {
         Self {
             storage,
-            interval: Duration::from_secs(60),
+            transport,
+            query_states: Mutex::new(HashMap::new()),
+            query_failures: Mutex::new(HashMap::new()),
+            held_until: Mutex::new(HashMap::new()),
+            viewer_teams: Mutex::new(HashMap::new()),
         }
     }
 
+    /// Sync in a loop, running each query on its own interval. Failed queries are retried with exponential backoff
+    /// (or when the API budget resets). The loop stops only when all the queries fail with fatal errors, like missing
+    /// credentials, so one host with a bad token does not stop the others. The syncs slow down when the API budget
+    /// runs low.
     pub async fn run_blocking(&self, config: &Config) -> Result<()> {
+        let default_interval = match config.sync_interval_seconds {
+            0 => DEFAULT_SYNC_INTERVAL,
+            n => Duration::from_secs(n.into()),
+        };
+        let query_interval = |query: &Query| match query.interval_seconds {
+            0 => default_interval,
+            n => Duration::from_secs(n.into()),
+        };
         loop {
-            if let Err(e) = self.run_once(config).await {
-                log::error!("Sync error: {}", e);
-                return Err(e);
+            let now = Utc::now();
+            let result = self
+                .run_queries(config, |query| {
+                    self.until_due(query, query_interval(query), now).is_zero()
+                })
+                .await;
+            if let Err(e) = result {
+                let kind = transport::classify(&e);
+                if self.all_failed_fatally(config) {
+                    log::error!("Sync error ({:?}), giving up: {}", kind, e);
+                    return Err(e);
+                }
+                log::error!("Sync error ({:?}): {}", kind, e);
             }
-            time::sleep(self.interval).await;
+            let now = Utc::now();
+            let until_next_due = config
+                .queries
+                .iter()
+                .map(|query| self.until_due(query, query_interval(query), now))
+                .min()
+                .unwrap_or(default_interval);
+            let delay = next_sync_delay(until_next_due, None, now);
+            let next_sync_at =
+                now + chrono::Duration::from_std(delay).unwrap_or(chrono::Duration::MAX);
+            if let Err(e) = self.write_schedule(next_sync_at) {
+                log::warn!("Could not write the sync schedule: {}", e);
+            }
+            time::sleep(delay).await;
         }
     }
 
+    /// Record in the sync status when the next sync runs and which hosts are paused until their API budget resets,
+    /// so that a sync that waits is not taken for one that stopped.
+    fn write_schedule(&self, next_sync_at: DateTime<Utc>) -> Result<()> {
+        let mut status = self.storage.get_sync_status()?;
+        status.next_sync_at = Some(next_sync_at);
+        status.paused_until = self
+            .held_until
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(host, until)| {
+                status
+                    .rate_limits
+                    .get(*host)
+                    .is_some_and(|r| **until >= r.reset_at)
+            })
+            .map(|(host, until)| (host.clone(), *until))
+            .collect();
+        self.storage.write_sync_status(&status)
+    }
+
+    /// Sync all the queries once.
     pub async fn run_once(&self, config: &Config) -> Result<()> {
+        self.run_queries(config, |_| true).await
+    }
+
+    fn until_due(&self, query: &Query, interval: Duration, now: DateTime<Utc>) -> Duration {
+        let query_states = self.query_states.lock().unwrap();
+        let query_failures = self.query_failures.lock().unwrap();
+        let held_until = self.held_until.lock().unwrap();
+        until_due(
+            query_states.get(&query.query_name),
+            query_failures.get(&query.query_name),
+            held_until.get(&host_name(query)).copied(),
+            interval,
+            now,
+        )
+    }
+
+    /// Hold off the next sync of the host according to its API budget, see [next_sync_delay].
+    fn hold_host(&self, host_name: &str, rate_limit: &RateLimit) {
+        let now = Utc::now();
+        let delay = next_sync_delay(Duration::ZERO, Some(rate_limit), now);
+        self.held_until.lock().unwrap().insert(
+            host_name.to_string(),
+            now + chrono::Duration::from_std(delay).unwrap_or(chrono::Duration::MAX),
+        );
+    }
+
+    /// Whether the last attempt of every query failed with a fatal error.
+    fn all_failed_fatally(&self, config: &Config) -> bool {
+        let query_failures = self.query_failures.lock().unwrap();
+        config.queries.iter().all(|query| {
+            query_failures
+                .get(&query.query_name)
+                .is_some_and(|f| f.kind.is_fatal())
+        })
+    }
+
+    /// Record the success of a query, with its results.
+    fn record_success(&self, query_name: &str, prs: Vec<PullRequest>) {
+        self.query_failures.lock().unwrap().remove(query_name);
+        self.query_states.lock().unwrap().insert(
+            query_name.to_string(),
+            QueryState {
+                synced_at: Utc::now(),
+                prs,
+            },
+        );
+    }
+
+    /// Record the failure of a query and schedule its retry: with backoff, or when the API budget resets if the
+    /// failure is due to the rate limit.
+    fn record_failure(&self, query_name: &str, kind: ErrorKind, rate_limit: Option<&RateLimit>) {
+        let now = Utc::now();
+        let mut query_failures = self.query_failures.lock().unwrap();
+        let count = query_failures.get(query_name).map_or(0, |f| f.count);
+        if kind == ErrorKind::Config {
+            log::error!("Query '{}' is invalid, not retrying it", query_name);
+            query_failures.insert(
+                query_name.to_string(),
+                QueryFailure {
+                    count: count.saturating_add(1),
+                    kind,
+                    retry_at: DateTime::<Utc>::MAX_UTC,
+                },
+            );
+            return;
+        }
+        let delay = match rate_limit.and_then(|r| until_reset(r, now)) {
+            Some(delay) if kind == ErrorKind::RateLimit => delay,
+            _ => backoff_delay(count),
+        };
+        log::warn!(
+            "Query '{}' failed {} time(s) in a row, retrying in {}s",
+            query_name,
+            count + 1,
+            delay.as_secs()
+        );
+        query_failures.insert(
+            query_name.to_string(),
+            QueryFailure {
+                count: count.saturating_add(1),
+                kind,
+                retry_at: now + chrono::Duration::from_std(delay).unwrap_or(chrono::Duration::MAX),
+            },
+        );
+    }
+
+    /// Refresh the queries for which `is_due` is true, merge them with the last results of the other queries, and
+    /// record the outcome with [Storage::write_sync_status].
+    async fn run_queries(&self, config: &Config, is_due: impl Fn(&Query) -> bool) -> Result<()> {
+        let started_at = Utc::now();
+        let due_queries: Vec<&Query> = config.queries.iter().filter(|q| is_due(q)).collect();
+        if due_queries.is_empty() {
+            return Ok(());
+        }
+
+        let mut status = self.storage.get_sync_status().unwrap_or_default();
+        status.last_attempt = Some(started_at);
+        for query in &due_queries {
+            status.query_counts.remove(&query.query_name);
+            status.query_errors.remove(&query.query_name);
+        }
+        let is_configured = |name: &String| config.queries.iter().any(|q| &q.query_name == name);
+        status.query_counts.retain(|name, _| is_configured(name));
+        status.query_errors.retain(|name, _| is_configured(name));
+
+        let result = self.sync_queries(config, &due_queries, &mut status).await;
+
+        status.duration_ms = (Utc::now() - started_at).num_milliseconds();
+        match &result {
+            Ok(()) => {
+                status.last_success = Some(started_at);
+                status.last_error.clear();
+            }
+            Err(e) => status.last_error = e.to_string(),
+        }
+        self.storage.write_sync_status(&status)?;
+        result
+    }
+
+    async fn sync_queries(
+        &self,
+        config: &Config,
+        due_queries: &[&Query],
+        status: &mut SyncStatus,
+    ) -> Result<()> {
         log::debug!("Run gh search");
 
-        let mut queried_prs: HashMap<String, Vec<PullRequest>> = HashMap::new();
+        let concurrency = match config.concurrency {
+            0 => DEFAULT_CONCURRENCY,
+            n => n as usize,
+        };
+        let query_timeout = match config.query_timeout_seconds {
+            0 => DEFAULT_QUERY_TIMEOUT,
+            n => Duration::from_secs(n.into()),
+        };
+        log::debug!(
+            "Run {} of {} queries, {} at once",
+            due_queries.len(),
+            config.queries.len(),
+            concurrency
+        );
+
+        let results: Vec<Result<QueryResult>> = stream::iter(due_queries)
+            .map(|query| self.run_query(query, query_timeout))
+            .buffered(concurrency)
+            .collect()
+            .await;
+        let mut results: HashMap<&str, Result<QueryResult>> = due_queries
+            .iter()
+            .map(|q| q.query_name.as_str())
+            .zip(results)
+            .collect();
 
+        let mut previous_prs: Option<Vec<PullRequest>> = None;
+        let mut rate_limits: HashMap<String, RateLimit> = HashMap::new();
+        let mut first_error: Option<anyhow::Error> = None;
+        let mut urls: Vec<String> = Vec::new();
+        let mut queried_prs: HashMap<String, Vec<PullRequest>> = HashMap::new();
+        // Merge in the order of the queries, so the dedup below does not depend on which query finished first.
         for query in &config.queries {
-            log::debug!("Querying: {} ({})", query.query_name, query.github_arg);
-            let prs = get_prs(&query.github_arg, &query.query_name, query.mute).await?;
+            let prs = match results.remove(query.query_name.as_str()) {
+                // Not due, e.g. waiting for the retry of a failure before its first success since the start.
+                None => match self.last_prs_of_query(&query.query_name) {
+                    Some(prs) => prs,
+                    None => mark_stale(self.stored_prs_of_query(query, &mut previous_prs)),
+                },
+                Some(Ok(result)) => {
+                    status
+                        .query_counts
+                        .insert(query.query_name.clone(), result.prs.len());
+                    let host = host_name(query);
+                    if let Some(rate_limit) =
+                        merge_rate_limits(rate_limits.remove(&host), result.rate_limit)
+                    {
+                        rate_limits.insert(host, rate_limit);
+                    }
+                    self.record_success(&query.query_name, result.prs.clone());
+                    result.prs
+                }
+                Some(Err(e)) => {
+                    status
+                        .query_errors
+                        .insert(query.query_name.clone(), e.to_string());
+                    let kind = transport::classify(&e);
+                    self.record_failure(
+                        &query.query_name,
+                        kind,
+                        status.rate_limits.get(&host_name(query)),
+                    );
+                    log::warn!(
+                        "Query '{}' failed, keeping its previous results: {}",
+                        query.query_name,
+                        e
+                    );
+                    first_error.get_or_insert(e);
+                    let prs = match self.last_prs_of_query(&query.query_name) {
+                        Some(prs) => prs,
+                        None => self.stored_prs_of_query(query, &mut previous_prs),
+                    };
+                    mark_stale(prs)
+                }
+            };
             log::debug!("Found {} PRs for query '{}'", prs.len(), query.query_name);
             for pr in prs {
-                queried_prs
-                    .entry(pr.url.clone())
-                    .or_insert_with(Vec::new)
-                    .push(pr);
+                let entry = queried_prs.entry(pr.url.clone()).or_default();
+                if entry.is_empty() {
+                    urls.push(pr.url.clone());
+                }
+                entry.push(pr);
             }
         }
 
+        for (host, rate_limit) in rate_limits {
+            log::debug!("Rate limit of {}: {:?}", host, rate_limit);
+            self.hold_host(&host, &rate_limit);
+            status.rate_limits.insert(host, rate_limit);
+        }
+        let hosts: HashSet<String> = config.queries.iter().map(host_name).collect();
+        status.rate_limits.retain(|host, _| hosts.contains(host));
+
         log::debug!("Got {} PRs (with duplicates)", queried_prs.len());
         log::debug!("Use attribution order: {:?}", config.attribution_order);
 
@@ -94,21 +617,421 @@ impl<S: Storage> Synchronizer<S> {
         }
 
         let mut unique_prs = Vec::new();
-        for prs in queried_prs.values() {
-            let selected = if prs.len() == 1 {
+        for url in &urls {
+            let prs = &queried_prs[url];
+            let mut selected = if prs.len() == 1 {
                 prs[0].clone()
             } else {
                 select_pr_with_attribution_priority(prs, &attribution_priority)
             };
+            selected.meta.queries = prs.iter().map(|pr| pr.meta.label.clone()).collect();
             unique_prs.push(selected);
         }
 
+        if let Some(e) = first_error {
+            let all_failed = due_queries
+                .iter()
+                .all(|q| status.query_errors.contains_key(&q.query_name));
+            if all_failed {
+                return Err(e);
+            }
+        }
+
+        let previous_prs =
+            previous_prs.unwrap_or_else(|| self.storage.get_pull_requests().unwrap_or_default());
+        self.record_departures(config, previous_prs, &unique_prs, query_timeout)
+            .await?;
+
         log::debug!("Storing {} unique pull requests", unique_prs.len());
         self.storage.reset_pull_requests(unique_prs.clone())?;
         log::debug!("Successfully updated {} pull requests", unique_prs.len());
 
         Ok(())
     }
+
+    /// Add the PRs that left the results since the last sync to the departed list, with the reason looked up from
+    /// their final state. The PRs that came back, and the departures older than [DEPARTED_RETENTION], are dropped.
+    async fn record_departures(
+        &self,
+        config: &Config,
+        previous_prs: Vec<PullRequest>,
+        prs: &[PullRequest],
+        timeout: Duration,
+    ) -> Result<()> {
+        let now = Utc::now();
+        let urls: HashSet<&str> = prs.iter().map(|pr| pr.url.as_str()).collect();
+        let vanished: Vec<PullRequest> = previous_prs
+            .into_iter()
+            .filter(|pr| !urls.contains(pr.url.as_str()))
+            .collect();
+
+        let mut departed = self
+            .storage
+            .get_departed_pull_requests()
+            .unwrap_or_default();
+        let departed_count = departed.len();
+        departed.retain(|d| {
+            !urls.contains(d.pr.url.as_str()) && now - d.departed_at < DEPARTED_RETENTION
+        });
+        if vanished.is_empty() && departed.len() == departed_count {
+            return Ok(());
+        }
+
+        let mut by_host: HashMap<Host, Vec<&PullRequest>> = HashMap::new();
+        for pr in &vanished {
+            by_host.entry(host_of(config, pr)).or_default().push(pr);
+        }
+        let mut reasons = HashMap::new();
+        for (host, prs) in by_host {
+            let lookup = get_departure_reasons(&self.transport, &host, &prs);
+            match time::timeout(timeout, lookup).await {
+                Ok(Ok(host_reasons)) => reasons.extend(host_reasons),
+                Ok(Err(e)) => {
+                    log::warn!(
+                        "Could not look up the state of departed PRs on {}: {}",
+                        host,
+                        e
+                    )
+                }
+                Err(_) => log::warn!("Looking up the state of departed PRs on {} timed out", host),
+            }
+        }
+        for pr in vanished {
+            let reason = reasons
+                .get(&pr.url)
+                .copied()
+                .unwrap_or(DepartureReason::Unknown);
+            log::info!("{} left the results: {}", pr.url, reason);
+            departed.retain(|d| d.pr.url != pr.url);
+            departed.push(DepartedPullRequest {
+                pr,
+                reason,
+                departed_at: now,
+            });
+        }
+        self.storage.write_departed_pull_requests(&departed)
+    }
+
+    /// The stored PRs that the query returned, for a query without a result since the start. The stored PRs are read
+    /// into `previous_prs` once.
+    fn stored_prs_of_query(
+        &self,
+        query: &Query,
+        previous_prs: &mut Option<Vec<PullRequest>>,
+    ) -> Vec<PullRequest> {
+        previous_prs
+            .get_or_insert_with(|| self.storage.get_pull_requests().unwrap_or_default())
+            .iter()
+            .filter(|pr| returned_by(pr, &query.query_name))
+            .map(|pr| {
+                let mut pr = pr.clone();
+                pr.meta.label = query.query_name.clone();
+                pr.meta.default_mute = query.mute;
+                pr
+            })
+            .collect()
+    }
+
+    fn last_prs_of_query(&self, query_name: &str) -> Option<Vec<PullRequest>> {
+        let query_states = self.query_states.lock().unwrap();
+        query_states.get(query_name).map(|state| state.prs.clone())
+    }
+
+    async fn run_query(&self, query: &Query, timeout: Duration) -> Result<QueryResult> {
+        log::debug!("Querying: {}", query.query_name);
+        let fetch = async {
+            let mut result = get_prs(&self.transport, query).await?;
+            self.mark_review_requests(query, &mut result).await?;
+            Ok::<_, anyhow::Error>(result)
+        };
+        let mut result = time::timeout(timeout, fetch).await.map_err(|_| {
+            TransportError::new(
+                ErrorKind::Network,
+                format!(
+                    "Query '{}' timed out after {}s",
+                    query.query_name,
+                    timeout.as_secs()
+                ),
+            )
+        })??;
+
+        // The review threads have their own timeout, so that the query succeeds even if there are many to fetch. The
+        // threads that are not fetched in time keep their earlier state and are fetched in the next sync.
+        let previous = self
+            .last_prs_of_query(&query.query_name)
+            .unwrap_or_else(|| self.storage.get_pull_requests().unwrap_or_default());
+        let to_fetch = reuse_review_threads(&mut result.prs, &previous);
+        let host = Host::new(&query.host, &query.token_env);
+        let fetch_threads = get_review_threads(
+            &self.transport,
+            &host,
+            &mut result.prs,
+            &to_fetch,
+            &mut result.rate_limit,
+        );
+        match time::timeout(timeout, fetch_threads).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => log::warn!(
+                "Could not fetch the review threads of query '{}', retrying in the next sync: {}",
+                query.query_name,
+                e
+            ),
+            Err(_) => log::warn!(
+                "Fetching the review threads of query '{}' timed out after {}s, continuing in the next sync",
+                query.query_name,
+                timeout.as_secs()
+            ),
+        }
+        Ok(result)
+    }
+
+    /// Tell how the viewer was asked to review each PR, directly or via a team, and keep only the PRs matching
+    /// `review_requested` of the query.
+    async fn mark_review_requests(&self, query: &Query, result: &mut QueryResult) -> Result<()> {
+        let host = Host::new(&query.host, &query.token_env);
+        let prs = &mut result.prs;
+        for pr in prs.iter_mut() {
+            let viewer = &pr.meta.viewer;
+            if viewer.is_empty() {
+                continue;
+            }
+            pr.meta.review_requested_from = if pr.review_requests.iter().any(|r| &r.login == viewer)
+            {
+                ReviewRequestedFrom::Directly
+            } else if pr
+                .review_requests
+                .iter()
+                .any(|r| !r.combined_slug.is_empty())
+            {
+                match self
+                    .viewer_teams(&host, viewer, &mut result.rate_limit)
+                    .await
+                {
+                    Ok(teams) => pr
+                        .review_requests
+                        .iter()
+                        .find(|r| teams.contains(&r.combined_slug))
+                        .map(|r| ReviewRequestedFrom::Team(r.combined_slug.clone()))
+                        .unwrap_or_default(),
+                    // Without the teams, the query cannot tell which PRs to keep, so it fails and keeps its previous
+                    // results instead of dropping all the team PRs.
+                    Err(e) if !query.review_requested.is_empty() => return Err(e),
+                    Err(_) => ReviewRequestedFrom::NotRequested,
+                }
+            } else {
+                ReviewRequestedFrom::NotRequested
+            };
+        }
+        match query.review_requested.as_str() {
+            "" => {}
+            REVIEW_REQUESTED_DIRECT => {
+                prs.retain(|pr| pr.meta.review_requested_from == ReviewRequestedFrom::Directly)
+            }
+            REVIEW_REQUESTED_TEAM => prs
+                .retain(|pr| matches!(pr.meta.review_requested_from, ReviewRequestedFrom::Team(_))),
+            other => {
+                return Err(TransportError::new(
+                    ErrorKind::Config,
+                    format!(
+                        "Invalid review_requested '{}' in query '{}'",
+                        other, query.query_name
+                    ),
+                )
+                .into())
+            }
+        }
+        Ok(())
+    }
+
+    /// Teams of the viewer on the host, fetched on first use. If they cannot be fetched (e.g. the token lacks the
+    /// read:org scope), the failure is returned again without a lookup until its retry, with the same backoff as the
+    /// queries. The cost of the lookup is added to `rate_limit`.
+    async fn viewer_teams(
+        &self,
+        host: &Host,
+        viewer: &str,
+        rate_limit: &mut Option<RateLimit>,
+    ) -> Result<Vec<String>> {
+        let now = Utc::now();
+        let mut count = 0;
+        match self.viewer_teams.lock().unwrap().get(host) {
+            Some(TeamsLookup::Resolved(teams)) => return Ok(teams.clone()),
+            Some(TeamsLookup::Failed { failure, error }) if failure.retry_at > now => {
+                return Err(TransportError::new(failure.kind, error.clone()).into());
+            }
+            Some(TeamsLookup::Failed { failure, .. }) => count = failure.count,
+            None => {}
+        }
+        match get_viewer_teams(&self.transport, host, viewer, rate_limit).await {
+            Ok(teams) => {
+                log::debug!("Teams of {} on {}: {:?}", viewer, host, teams);
+                self.viewer_teams
+                    .lock()
+                    .unwrap()
+                    .insert(host.clone(), TeamsLookup::Resolved(teams.clone()));
+                Ok(teams)
+            }
+            Err(e) => {
+                let kind = transport::classify(&e);
+                let error = format!(
+                    "Could not resolve the teams of {} on {}: {}",
+                    viewer, host, e
+                );
+                let delay = backoff_delay(count);
+                log::warn!("{}, retrying in {}s", error, delay.as_secs());
+                let failure = QueryFailure {
+                    count: count.saturating_add(1),
+                    kind,
+                    retry_at: now
+                        + chrono::Duration::from_std(delay).unwrap_or(chrono::Duration::MAX),
+                };
+                self.viewer_teams.lock().unwrap().insert(
+                    host.clone(),
+                    TeamsLookup::Failed {
+                        failure,
+                        error: error.clone(),
+                    },
+                );
+                Err(TransportError::new(kind, error).into())
+            }
+        }
+    }
+}
+
+async fn get_viewer_teams<T: Transport>(
+    transport: &T,
+    host: &Host,
+    viewer: &str,
+    rate_limit: &mut Option<RateLimit>,
+) -> Result<Vec<String>> {
+    let mut data = transport
+        .graphql(host, VIEWER_TEAMS_QUERY, json!({ "login": viewer }))
+        .await?;
+    add_cost(rate_limit, &mut data)?;
+    let organizations = data
+        .pointer_mut("/viewer/organizations")
+        .map(|v| v.take())
+        .ok_or_else(|| anyhow::anyhow!("No organizations in response"))?;
+    let organizations: GqlConnection<GqlOrganization> = serde_json::from_value(organizations)?;
+    Ok(organizations
+        .nodes
+        .into_iter()
+        .flat_map(|o| o.teams.nodes)
+        .map(|t| t.combined_slug)
+        .collect())
+}
+
+/// Time until the query should be refreshed: after its interval since the last success, or at the retry time if it
+/// failed since, but not before its host is `held_until`. Zero if it never ran and the host is not held.
+fn until_due(
+    state: Option<&QueryState>,
+    failure: Option<&QueryFailure>,
+    held_until: Option<DateTime<Utc>>,
+    interval: Duration,
+    now: DateTime<Utc>,
+) -> Duration {
+    let due_at = match (failure, state) {
+        (Some(failure), _) => Some(failure.retry_at),
+        (None, Some(state)) => Some(state.synced_at + interval),
+        (None, None) => None,
+    };
+    match due_at.max(held_until) {
+        Some(due_at) => (due_at - now).to_std().unwrap_or(Duration::ZERO),
+        None => Duration::ZERO,
+    }
+}
+
+/// The host of the query, which has its own API budget.
+fn host_name(query: &Query) -> String {
+    Host::new(&query.host, &query.token_env).to_string()
+}
+
+/// The delay before the next sync, at least [MIN_SYNC_DELAY]. If the API budget left is not enough for the syncs
+/// until the budget resets, the syncs are spread evenly until the reset. If the budget is almost exhausted, the sync
+/// pauses until the reset.
+fn next_sync_delay(
+    interval: Duration,
+    rate_limit: Option<&RateLimit>,
+    now: DateTime<Utc>,
+) -> Duration {
+    let interval = interval.max(MIN_SYNC_DELAY);
+    let Some(rate_limit) = rate_limit else {
+        return interval;
+    };
+    let Some(until_reset) = until_reset(rate_limit, now) else {
+        return interval;
+    };
+    let sync_cost = rate_limit.cost.max(1);
+    if rate_limit.remaining < sync_cost * RATE_LIMIT_RESERVE_SYNCS {
+        log::warn!(
+            "Rate limit almost exhausted ({} remaining), pausing until {}",
+            rate_limit.remaining,
+            rate_limit.reset_at
+        );
+        return until_reset;
+    }
+    let syncs_left = (rate_limit.remaining / sync_cost) as u32;
+    let spread = until_reset / syncs_left;
+    if spread > interval {
+        log::info!(
+            "Rate limit running low ({} remaining), syncing every {}s",
+            rate_limit.remaining,
+            spread.as_secs()
+        );
+        spread
+    } else {
+        interval
+    }
+}
+
+/// Time left until the API budget resets, None if it already did.
+fn until_reset(rate_limit: &RateLimit, now: DateTime<Utc>) -> Option<Duration> {
+    (rate_limit.reset_at - now)
+        .to_std()
+        .ok()
+        .filter(|d| !d.is_zero())
+}
+
+/// Combine the budgets reported by GitHub for several requests to the same host: the costs add up, and the lowest
+/// remaining budget is the most recent one.
+fn merge_rate_limits(a: Option<RateLimit>, b: Option<RateLimit>) -> Option<RateLimit> {
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            let cost = a.cost + b.cost;
+            let latest = if a.remaining <= b.remaining { a } else { b };
+            Some(RateLimit { cost, ..latest })
+        }
+        (a, b) => a.or(b),
+    }
+}
+
+/// Exponential backoff with "equal jitter": the delay is randomly chosen from the upper half of the exponential
+/// delay, so that the retries do not synchronize.
+fn backoff_delay(failures: u32) -> Duration {
+    let exp = RETRY_BASE_DELAY
+        .saturating_mul(2u32.saturating_pow(failures))
+        .min(RETRY_MAX_DELAY);
+    let half = exp / 2;
+    half + half.mul_f64(fastrand::f64())
+}
+
+/// Whether the query returned the PR in the sync that stored it. The PRs stored before [Meta::queries] was recorded
+/// only know the query they are attributed to.
+fn returned_by(pr: &PullRequest, query_name: &str) -> bool {
+    if pr.meta.queries.is_empty() {
+        pr.meta.label == query_name
+    } else {
+        pr.meta.queries.iter().any(|q| q == query_name)
+    }
+}
+
+fn mark_stale(prs: Vec<PullRequest>) -> Vec<PullRequest> {
+    prs.into_iter()
+        .map(|mut pr| {
+            pr.meta.stale = true;
+            pr
+        })
+        .collect()
 }
 
 fn select_pr_with_attribution_priority(
@@ -135,49 +1058,325 @@ fn select_pr_with_attribution_priority(
     selected
 }
 
-async fn get_prs(github_arg: &str, meta_label: &str, mute: bool) -> Result<Vec<PullRequest>> {
-    let search_qualifier = github_arg.trim_start_matches("--").replacen('=', ":", 1);
-    let search_query = format!("is:pr is:open draft:false {}", search_qualifier);
+/// Run the search of the query. The review threads are left to [get_review_threads].
+async fn get_prs<T: Transport>(transport: &T, query: &Query) -> Result<QueryResult> {
+    let search_qualifiers = query
+        .search_qualifiers()
+        .map_err(|e| TransportError::new(ErrorKind::Config, e.to_string()))?;
+    let type_qualifier = match (query.kind.as_str(), query.drafts.as_str()) {
+        ("" | KIND_PR, "" | DRAFTS_EXCLUDE) => "is:pr draft:false",
+        ("" | KIND_PR, DRAFTS_INCLUDE) => "is:pr",
+        ("" | KIND_PR, DRAFTS_ONLY) => "is:pr draft:true",
+        (KIND_ISSUE, _) => "is:issue",
+        (kind, drafts) => {
+            return Err(TransportError::new(
+                ErrorKind::Config,
+                format!(
+                    "Invalid kind '{}' or drafts '{}' in query '{}'",
+                    kind, drafts, query.query_name
+                ),
+            )
+            .into())
+        }
+    };
+    let search_query = format!("{} is:open {}", type_qualifier, search_qualifiers);
+    let max_results = match query.max_results {
+        0 => DEFAULT_MAX_RESULTS,
+        n => n as usize,
+    };
+
+    let host = Host::new(&query.host, &query.token_env);
 
-    log::debug!("Executing GraphQL search: {}", search_query);
+    log::debug!("Executing GraphQL search on {}: {}", host, search_query);
 
-    let output = Command::new("gh")
-        .args([
-            "api",
-            "graphql",
-            "-f",
-            &format!("query={}", GRAPHQL_QUERY),
-            "-f",
-            &format!("q={}", search_query),
-        ])
-        .output()?;
+    let mut nodes: Vec<GqlPullRequest> = Vec::new();
+    let mut rate_limit: Option<RateLimit> = None;
+    let mut viewer = String::new();
+    let mut after: Option<String> = None;
+    loop {
+        let first = PAGE_SIZE.min(max_results - nodes.len());
+        let data = transport
+            .graphql(
+                &host,
+                GRAPHQL_QUERY,
+                json!({ "q": search_query, "first": first, "after": after }),
+            )
+            .await?;
+        let response: GqlData = serde_json::from_value(data)?;
+        rate_limit = merge_rate_limits(rate_limit, response.rate_limit);
+        if let Some(v) = response.viewer {
+            viewer = v.login;
+        }
+        let search = response.search;
+        nodes.extend(search.nodes);
 
-    if !output.status.success() {
-        return Err(anyhow::anyhow!(
-            "gh api graphql failed: {}",
-            String::from_utf8_lossy(&output.stderr)
+        if !search.page_info.has_next_page {
+            break;
+        }
+        if nodes.len() >= max_results {
+            log::warn!(
+                "Query '{}' truncated to {} of {} results",
+                query.query_name,
+                nodes.len(),
+                search.issue_count
+            );
+            break;
+        }
+        if search.page_info.end_cursor.is_none() || search.page_info.end_cursor == after {
+            log::warn!(
+                "Query '{}' stopped at {} of {} results, GitHub returned no next cursor",
+                query.query_name,
+                nodes.len(),
+                search.issue_count
+            );
+            break;
+        }
+        after = search.page_info.end_cursor;
+    }
+
+    let mut prs = Vec::with_capacity(nodes.len());
+    for mut node in nodes {
+        let cost = fetch_remaining_pages(
+            transport,
+            &host,
+            REVIEW_REQUESTS_QUERY,
+            "reviewRequests",
+            &node.id,
+            &mut node.review_requests,
+        )
+        .await?;
+        rate_limit = merge_rate_limits(rate_limit, cost);
+        let cost = fetch_remaining_pages(
+            transport,
+            &host,
+            LATEST_REVIEWS_QUERY,
+            "latestReviews",
+            &node.id,
+            &mut node.latest_reviews,
+        )
+        .await?;
+        rate_limit = merge_rate_limits(rate_limit, cost);
+        prs.push(to_pull_request(
+            node,
+            &query.query_name,
+            query.mute,
+            &viewer,
         ));
     }
 
-    let response: GqlResponse = serde_json::from_slice(&output.stdout)?;
+    Ok(QueryResult { prs, rate_limit })
+}
 
-    let prs = response
-        .data
-        .search
+/// Take the review threads of the PRs that did not change since the `previous` sync from there, and return the
+/// indices of the PRs whose review threads need fetching. Those keep their earlier review threads until fetched.
+fn reuse_review_threads(prs: &mut [PullRequest], previous: &[PullRequest]) -> Vec<usize> {
+    let mut to_fetch = Vec::new();
+    for (i, pr) in prs.iter_mut().enumerate() {
+        let previous = previous.iter().find(|p| p.id == pr.id);
+        match previous {
+            _ if pr.review_threads_count == 0 => set_review_threads(pr, Vec::new()),
+            Some(previous)
+                if previous.review_threads_fetched && previous.updated_at == pr.updated_at =>
+            {
+                copy_review_threads(pr, previous);
+                pr.review_threads_fetched = true;
+            }
+            _ => {
+                if let Some(previous) = previous {
+                    copy_review_threads(pr, previous);
+                }
+                to_fetch.push(i);
+            }
+        }
+    }
+    to_fetch
+}
+
+/// Fetch the review threads of the PRs at the indices `to_fetch`, in batches. Their cost is added to `rate_limit`.
+async fn get_review_threads<T: Transport>(
+    transport: &T,
+    host: &Host,
+    prs: &mut [PullRequest],
+    to_fetch: &[usize],
+    rate_limit: &mut Option<RateLimit>,
+) -> Result<()> {
+    for batch in to_fetch.chunks(REVIEW_THREADS_BATCH_SIZE) {
+        let ids: Vec<&str> = batch.iter().map(|&i| prs[i].id.as_str()).collect();
+        let mut data = transport
+            .graphql(host, REVIEW_THREADS_BATCH_QUERY, json!({ "ids": ids }))
+            .await?;
+        add_cost(rate_limit, &mut data)?;
+        let response: GqlReviewThreadsNodes = serde_json::from_value(data)?;
+        // The nodes come in the order of the ids.
+        for (&i, node) in batch.iter().zip(response.nodes) {
+            let Some(mut node) = node else {
+                continue;
+            };
+            let cost = fetch_remaining_pages(
+                transport,
+                host,
+                REVIEW_THREADS_QUERY,
+                "reviewThreads",
+                &prs[i].id,
+                &mut node.review_threads,
+            )
+            .await?;
+            *rate_limit = merge_rate_limits(rate_limit.take(), cost);
+            set_review_threads(&mut prs[i], node.review_threads.nodes);
+        }
+    }
+    Ok(())
+}
+
+/// Set what is told by the review threads of the PR: the review comments, the unresolved threads and the most recent
+/// review comments in the timeline.
+fn set_review_threads(pr: &mut PullRequest, threads: Vec<GqlReviewThread>) {
+    pr.review_comments_count = threads.iter().map(|t| t.comments.total_count).sum();
+    pr.unresolved_threads = threads.iter().filter(|t| !t.is_resolved).count() as i32;
+    pr.timeline
+        .retain(|e| e.kind != TimelineEventKind::ReviewComment);
+    pr.timeline.extend(threads.into_iter().flat_map(|t| {
+        t.comments.nodes.into_iter().map(|c| TimelineEvent {
+            kind: TimelineEventKind::ReviewComment,
+            actor: c.author.map(|a| a.login).unwrap_or_default(),
+            created_at: c.created_at,
+            detail: String::new(),
+        })
+    }));
+    pr.timeline.sort_by_key(|e| e.created_at);
+    pr.review_threads_fetched = true;
+}
+
+/// Copy what was fetched from the review threads of the same PR in an earlier sync.
+fn copy_review_threads(pr: &mut PullRequest, previous: &PullRequest) {
+    pr.review_comments_count = previous.review_comments_count;
+    pr.unresolved_threads = previous.unresolved_threads;
+    pr.timeline
+        .retain(|e| e.kind != TimelineEventKind::ReviewComment);
+    pr.timeline.extend(
+        previous
+            .timeline
+            .iter()
+            .filter(|e| e.kind == TimelineEventKind::ReviewComment)
+            .cloned(),
+    );
+    pr.timeline.sort_by_key(|e| e.created_at);
+}
+
+/// Add the cost of a query to `rate_limit`, from the `rateLimit` of its response `data`.
+fn add_cost(rate_limit: &mut Option<RateLimit>, data: &mut serde_json::Value) -> Result<()> {
+    let cost = match data.get_mut("rateLimit") {
+        Some(value) => serde_json::from_value(value.take())?,
+        None => None,
+    };
+    *rate_limit = merge_rate_limits(rate_limit.take(), cost);
+    Ok(())
+}
+
+/// The host to query about the PR, with the token of the configured queries for the same host.
+fn host_of(config: &Config, pr: &PullRequest) -> Host {
+    config
+        .queries
+        .iter()
+        .map(|q| Host::new(&q.host, &q.token_env))
+        .find(|host| host.to_string() == pr.host())
+        .unwrap_or_else(|| Host::new(pr.host(), ""))
+}
+
+/// Look up why the PRs (or issues) of the host left the results, by URL.
+async fn get_departure_reasons<T: Transport>(
+    transport: &T,
+    host: &Host,
+    prs: &[&PullRequest],
+) -> Result<HashMap<String, DepartureReason>> {
+    let mut reasons = HashMap::new();
+    for chunk in prs.chunks(PAGE_SIZE) {
+        let ids: Vec<&str> = chunk.iter().map(|pr| pr.id.as_str()).collect();
+        let data = transport
+            .graphql_allow_not_found(host, FINAL_STATE_QUERY, json!({ "ids": ids }))
+            .await?;
+        let response: GqlNodes = serde_json::from_value(data)?;
+        // The nodes come in the order of the ids, null for the nodes that were not found.
+        for (pr, node) in chunk.iter().zip(response.nodes) {
+            let reason = match node.as_ref().map(|n| n.state.as_str()) {
+                None => DepartureReason::Deleted,
+                Some("MERGED") => DepartureReason::Merged,
+                Some("CLOSED") => DepartureReason::Closed,
+                Some("OPEN") => DepartureReason::Unmatched,
+                Some(_) => DepartureReason::Unknown,
+            };
+            reasons.insert(pr.url.clone(), reason);
+        }
+    }
+    Ok(reasons)
+}
+
+/// Fetch the pages of a connection nested in a PR that did not fit into the search response. The `field` is the
+/// name of the connection in the `query`. Returns the cost of the pages fetched.
+async fn fetch_remaining_pages<T: Transport, N: DeserializeOwned>(
+    transport: &T,
+    host: &Host,
+    query: &str,
+    field: &str,
+    pr_id: &str,
+    connection: &mut GqlConnection<N>,
+) -> Result<Option<RateLimit>> {
+    let mut rate_limit = None;
+    let mut pages = 1;
+    while connection.page_info.has_next_page {
+        if pages >= MAX_NESTED_PAGES {
+            log::warn!(
+                "{} of PR {} truncated to {} items",
+                field,
+                pr_id,
+                connection.nodes.len()
+            );
+            break;
+        }
+        let mut data = transport
+            .graphql(
+                host,
+                query,
+                json!({ "id": pr_id, "after": connection.page_info.end_cursor }),
+            )
+            .await?;
+        add_cost(&mut rate_limit, &mut data)?;
+        let page = data
+            .pointer_mut(&format!("/node/{}", field))
+            .map(|v| v.take())
+            .ok_or_else(|| anyhow::anyhow!("No {} in response for PR {}", field, pr_id))?;
+        let page: GqlConnection<N> = serde_json::from_value(page)?;
+        let stuck = page.page_info.end_cursor.is_none()
+            || page.page_info.end_cursor == connection.page_info.end_cursor;
+        connection.nodes.extend(page.nodes);
+        connection.page_info = page.page_info;
+        pages += 1;
+        if stuck && connection.page_info.has_next_page {
+            log::warn!(
+                "{} of PR {} stopped at {} items, GitHub returned no next cursor",
+                field,
+                pr_id,
+                connection.nodes.len()
+            );
+            break;
+        }
+    }
+    Ok(rate_limit)
+}
+
+/// The PR without its review threads, see [reuse_review_threads].
+fn to_pull_request(gql: GqlPullRequest, meta_label: &str, mute: bool, viewer: &str) -> PullRequest {
+    let mut timeline: Vec<TimelineEvent> = gql
+        .timeline_items
         .nodes
         .into_iter()
-        .map(|node| to_pull_request(node, meta_label, mute))
+        .filter_map(to_timeline_event)
         .collect();
+    timeline.sort_by_key(|e| e.created_at);
 
-    Ok(prs)
-}
-
-fn to_pull_request(gql: GqlPullRequest, meta_label: &str, mute: bool) -> PullRequest {
     PullRequest {
-        author: Author {
-            login: gql.author.map(|a| a.login).unwrap_or_default(),
-            ..Default::default()
-        },
+        kind: gql.typename,
+        author: gql.author.map(to_author).unwrap_or_default(),
         body: gql.body.unwrap_or_default(),
         comments_count: gql.comments.total_count,
         created_at: gql.created_at,
@@ -197,6 +1396,7 @@ fn to_pull_request(gql: GqlPullRequest, meta_label: &str, mute: bool) -> PullReq
                 login: r.login,
                 name: r.name,
                 slug: r.slug,
+                combined_slug: r.combined_slug,
             })
             .collect(),
         latest_reviews: gql
@@ -206,35 +1406,240 @@ fn to_pull_request(gql: GqlPullRequest, meta_label: &str, mute: bool) -> PullReq
             .map(|r| Review {
                 author_login: r.author.map(|a| a.login).unwrap_or_default(),
                 state: r.state,
+                commit_oid: r.commit.map(|c| c.oid).unwrap_or_default(),
             })
             .collect(),
+        checks: to_checks(gql.commits),
+        is_draft: gql.is_draft,
+        review_decision: gql.review_decision.unwrap_or_default(),
+        mergeable: gql.mergeable.unwrap_or_default(),
+        merge_state_status: gql.merge_state_status.unwrap_or_default(),
+        labels: gql.labels.nodes.into_iter().map(|l| l.name).collect(),
+        assignees: gql.assignees.nodes.into_iter().map(|a| a.login).collect(),
+        milestone: gql.milestone.map(|m| m.title).unwrap_or_default(),
+        additions: gql.additions,
+        deletions: gql.deletions,
+        changed_files: gql.changed_files,
+        closing_issues: gql.closing_issues_references.nodes,
+        base_ref_name: gql.base_ref_name.unwrap_or_default(),
+        head_ref_name: gql.head_ref_name.unwrap_or_default(),
+        head_ref_oid: gql.head_ref_oid.unwrap_or_default(),
+        is_cross_repository: gql.is_cross_repository,
+        review_comments_count: 0,
+        unresolved_threads: 0,
+        review_threads_count: gql.review_threads.total_count,
+        review_threads_fetched: false,
+        timeline,
         meta: Meta {
             label: meta_label.to_string(),
             default_mute: mute,
+            viewer: viewer.to_string(),
+            ..Default::default()
         },
     }
 }
 
-#[derive(Deserialize)]
-struct GqlResponse {
-    data: GqlData,
+fn to_author(actor: GqlActor) -> Author {
+    let is_bot = actor.typename == "Bot" || actor.login.ends_with("[bot]");
+    Author {
+        id: actor.id,
+        is_bot,
+        login: actor.login,
+        author_type: actor.typename,
+        url: actor.url,
+    }
+}
+
+fn to_timeline_event(item: GqlTimelineItem) -> Option<TimelineEvent> {
+    let login = |author: Option<GqlAuthor>| author.map(|a| a.login).unwrap_or_default();
+    let reviewer = |r: Option<GqlRequestedReviewer>| {
+        r.map(|r| if r.login.is_empty() { r.name } else { r.login })
+            .unwrap_or_default()
+    };
+    let (kind, actor, created_at, detail) = match item {
+        GqlTimelineItem::PullRequestCommit { commit } => {
+            let author = commit
+                .author
+                .map(|a| match a.user {
+                    Some(user) => user.login,
+                    None => a.name.unwrap_or_default(),
+                })
+                .unwrap_or_default();
+            (
+                TimelineEventKind::Commit,
+                author,
+                commit.committed_date,
+                String::new(),
+            )
+        }
+        GqlTimelineItem::IssueComment { author, created_at } => (
+            TimelineEventKind::Comment,
+            login(author),
+            created_at,
+            String::new(),
+        ),
+        GqlTimelineItem::PullRequestReview {
+            author,
+            created_at,
+            state,
+        } => (TimelineEventKind::Review, login(author), created_at, state),
+        GqlTimelineItem::LabeledEvent {
+            actor,
+            created_at,
+            label,
+        } => (
+            TimelineEventKind::Labeled,
+            login(actor),
+            created_at,
+            label.name,
+        ),
+        GqlTimelineItem::UnlabeledEvent {
+            actor,
+            created_at,
+            label,
+        } => (
+            TimelineEventKind::Unlabeled,
+            login(actor),
+            created_at,
+            label.name,
+        ),
+        GqlTimelineItem::ReviewRequestedEvent {
+            actor,
+            created_at,
+            requested_reviewer,
+        } => (
+            TimelineEventKind::ReviewRequested,
+            login(actor),
+            created_at,
+            reviewer(requested_reviewer),
+        ),
+        GqlTimelineItem::ReviewRequestRemovedEvent {
+            actor,
+            created_at,
+            requested_reviewer,
+        } => (
+            TimelineEventKind::ReviewRequestRemoved,
+            login(actor),
+            created_at,
+            reviewer(requested_reviewer),
+        ),
+        GqlTimelineItem::HeadRefForcePushedEvent { actor, created_at } => (
+            TimelineEventKind::ForcePushed,
+            login(actor),
+            created_at,
+            String::new(),
+        ),
+        GqlTimelineItem::Other => return None,
+    };
+    Some(TimelineEvent {
+        kind,
+        actor,
+        created_at,
+        detail,
+    })
+}
+
+/// Checks of the last commit of the PR.
+fn to_checks(commits: GqlCommits) -> Checks {
+    let Some(rollup) = commits
+        .nodes
+        .into_iter()
+        .last()
+        .and_then(|n| n.commit.status_check_rollup)
+    else {
+        return Checks::default();
+    };
+    let failing = rollup
+        .contexts
+        .nodes
+        .into_iter()
+        .filter_map(|c| match c {
+            GqlCheckContext::CheckRun { name, conclusion } => {
+                let failed = matches!(
+                    conclusion.as_deref(),
+                    Some(
+                        "FAILURE"
+                            | "TIMED_OUT"
+                            | "CANCELLED"
+                            | "ACTION_REQUIRED"
+                            | "STARTUP_FAILURE"
+                    )
+                );
+                failed.then_some(name)
+            }
+            GqlCheckContext::StatusContext { context, state } => {
+                matches!(state.as_str(), "FAILURE" | "ERROR").then_some(context)
+            }
+            GqlCheckContext::Other {} => None,
+        })
+        .collect();
+    Checks {
+        state: rollup.state,
+        failing,
+    }
 }
 
 #[derive(Deserialize)]
 struct GqlData {
+    #[serde(rename = "rateLimit", default)]
+    rate_limit: Option<RateLimit>,
+    #[serde(default)]
+    viewer: Option<GqlAuthor>,
     search: GqlSearch,
 }
 
+#[derive(Deserialize)]
+struct GqlNodes {
+    nodes: Vec<Option<GqlFinalState>>,
+}
+
+#[derive(Deserialize)]
+struct GqlFinalState {
+    #[serde(default)]
+    state: String,
+}
+
 #[derive(Deserialize)]
 struct GqlSearch {
+    #[serde(rename = "issueCount", default)]
+    issue_count: i32,
+    #[serde(rename = "pageInfo", default)]
+    page_info: GqlPageInfo,
     nodes: Vec<GqlPullRequest>,
 }
 
+#[derive(Deserialize, Default)]
+struct GqlPageInfo {
+    #[serde(rename = "hasNextPage")]
+    has_next_page: bool,
+    #[serde(rename = "endCursor")]
+    end_cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GqlConnection<N> {
+    #[serde(rename = "pageInfo", default)]
+    page_info: GqlPageInfo,
+    nodes: Vec<N>,
+}
+
+impl<N> Default for GqlConnection<N> {
+    fn default() -> Self {
+        Self {
+            page_info: GqlPageInfo::default(),
+            nodes: Vec::new(),
+        }
+    }
+}
+
+/// A pull request or an issue. The fields specific to pull requests are missing for issues.
 #[derive(Deserialize)]
 struct GqlPullRequest {
-    author: Option<GqlAuthor>,
+    #[serde(rename = "__typename")]
+    typename: ItemKind,
+    author: Option<GqlActor>,
     body: Option<String>,
-    comments: GqlComments,
+    comments: GqlTotalCount,
     #[serde(rename = "createdAt")]
     created_at: DateTime<Utc>,
     id: String,
@@ -245,26 +1650,226 @@ struct GqlPullRequest {
     updated_at: DateTime<Utc>,
     url: String,
     state: String,
-    #[serde(rename = "reviewRequests")]
-    review_requests: GqlReviewRequests,
-    #[serde(rename = "latestReviews")]
-    latest_reviews: GqlLatestReviews,
+    #[serde(rename = "reviewRequests", default)]
+    review_requests: GqlConnection<GqlReviewRequestNode>,
+    #[serde(rename = "latestReviews", default)]
+    latest_reviews: GqlConnection<GqlReviewNode>,
+    #[serde(default)]
+    commits: GqlCommits,
+    #[serde(rename = "isDraft", default)]
+    is_draft: bool,
+    #[serde(default)]
+    labels: GqlConnection<GqlLabel>,
+    #[serde(default)]
+    assignees: GqlConnection<GqlAuthor>,
+    #[serde(default)]
+    milestone: Option<GqlMilestone>,
+    #[serde(default)]
+    additions: i32,
+    #[serde(default)]
+    deletions: i32,
+    #[serde(rename = "changedFiles", default)]
+    changed_files: i32,
+    /// Only the first 3: the API budget of a search grows with the page size of each nested connection times the
+    /// number of results, and PRs rarely close more issues.
+    #[serde(rename = "closingIssuesReferences", default)]
+    closing_issues_references: GqlConnection<LinkedIssue>,
+    #[serde(rename = "baseRefName", default)]
+    base_ref_name: Option<String>,
+    #[serde(rename = "headRefName", default)]
+    head_ref_name: Option<String>,
+    #[serde(rename = "headRefOid", default)]
+    head_ref_oid: Option<String>,
+    #[serde(rename = "isCrossRepository", default)]
+    is_cross_repository: bool,
+    #[serde(rename = "reviewDecision", default)]
+    review_decision: Option<String>,
+    #[serde(default)]
+    mergeable: Option<String>,
+    #[serde(rename = "mergeStateStatus", default)]
+    merge_state_status: Option<String>,
+    #[serde(rename = "timelineItems", default)]
+    timeline_items: GqlConnection<GqlTimelineItem>,
+    #[serde(rename = "reviewThreads", default)]
+    review_threads: GqlTotalCount,
 }
 
 #[derive(Deserialize)]
-struct GqlAuthor {
-    login: String,
+struct GqlReviewThreadsNodes {
+    /// Null for the PRs that were not found.
+    nodes: Vec<Option<GqlReviewThreadsNode>>,
+}
+
+#[derive(Deserialize)]
+struct GqlReviewThreadsNode {
+    #[serde(rename = "reviewThreads")]
+    review_threads: GqlConnection<GqlReviewThread>,
+}
+
+#[derive(Deserialize)]
+struct GqlReviewThread {
+    #[serde(rename = "isResolved")]
+    is_resolved: bool,
+    comments: GqlReviewThreadComments,
 }
 
 #[derive(Deserialize)]
-struct GqlComments {
+struct GqlReviewThreadComments {
     #[serde(rename = "totalCount")]
     total_count: i32,
+    nodes: Vec<GqlReviewThreadComment>,
+}
+
+#[derive(Deserialize)]
+struct GqlReviewThreadComment {
+    author: Option<GqlAuthor>,
+    #[serde(rename = "createdAt")]
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "__typename")]
+enum GqlTimelineItem {
+    PullRequestCommit {
+        commit: GqlTimelineCommit,
+    },
+    IssueComment {
+        author: Option<GqlAuthor>,
+        #[serde(rename = "createdAt")]
+        created_at: DateTime<Utc>,
+    },
+    PullRequestReview {
+        author: Option<GqlAuthor>,
+        #[serde(rename = "createdAt")]
+        created_at: DateTime<Utc>,
+        state: String,
+    },
+    LabeledEvent {
+        actor: Option<GqlAuthor>,
+        #[serde(rename = "createdAt")]
+        created_at: DateTime<Utc>,
+        label: GqlLabel,
+    },
+    UnlabeledEvent {
+        actor: Option<GqlAuthor>,
+        #[serde(rename = "createdAt")]
+        created_at: DateTime<Utc>,
+        label: GqlLabel,
+    },
+    ReviewRequestedEvent {
+        actor: Option<GqlAuthor>,
+        #[serde(rename = "createdAt")]
+        created_at: DateTime<Utc>,
+        #[serde(rename = "requestedReviewer")]
+        requested_reviewer: Option<GqlRequestedReviewer>,
+    },
+    ReviewRequestRemovedEvent {
+        actor: Option<GqlAuthor>,
+        #[serde(rename = "createdAt")]
+        created_at: DateTime<Utc>,
+        #[serde(rename = "requestedReviewer")]
+        requested_reviewer: Option<GqlRequestedReviewer>,
+    },
+    HeadRefForcePushedEvent {
+        actor: Option<GqlAuthor>,
+        #[serde(rename = "createdAt")]
+        created_at: DateTime<Utc>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct GqlTimelineCommit {
+    #[serde(rename = "committedDate")]
+    committed_date: DateTime<Utc>,
+    author: Option<GqlCommitAuthor>,
+}
+
+#[derive(Deserialize)]
+struct GqlCommitAuthor {
+    name: Option<String>,
+    user: Option<GqlAuthor>,
+}
+
+#[derive(Deserialize)]
+struct GqlLabel {
+    name: String,
 }
 
 #[derive(Deserialize)]
-struct GqlReviewRequests {
-    nodes: Vec<GqlReviewRequestNode>,
+struct GqlOrganization {
+    teams: GqlConnection<GqlTeam>,
+}
+
+#[derive(Deserialize)]
+struct GqlTeam {
+    #[serde(rename = "combinedSlug")]
+    combined_slug: String,
+}
+
+#[derive(Deserialize)]
+struct GqlMilestone {
+    title: String,
+}
+
+#[derive(Deserialize, Default)]
+struct GqlCommits {
+    nodes: Vec<GqlCommitNode>,
+}
+
+#[derive(Deserialize)]
+struct GqlCommitNode {
+    commit: GqlCommit,
+}
+
+#[derive(Deserialize)]
+struct GqlCommit {
+    #[serde(rename = "statusCheckRollup")]
+    status_check_rollup: Option<GqlStatusCheckRollup>,
+}
+
+#[derive(Deserialize)]
+struct GqlStatusCheckRollup {
+    state: String,
+    contexts: GqlConnection<GqlCheckContext>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum GqlCheckContext {
+    CheckRun {
+        name: String,
+        conclusion: Option<String>,
+    },
+    StatusContext {
+        context: String,
+        state: String,
+    },
+    Other {},
+}
+
+/// The author of a PR: a User, a Bot (e.g. Dependabot), a Mannequin or an EnterpriseUserAccount.
+#[derive(Deserialize)]
+struct GqlActor {
+    #[serde(rename = "__typename", default)]
+    typename: String,
+    login: String,
+    #[serde(default)]
+    url: String,
+    #[serde(default)]
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct GqlAuthor {
+    login: String,
+}
+
+#[derive(Deserialize, Default)]
+struct GqlTotalCount {
+    #[serde(rename = "totalCount")]
+    total_count: i32,
 }
 
 #[derive(Deserialize)]
@@ -281,15 +1886,681 @@ struct GqlRequestedReviewer {
     name: String,
     #[serde(default)]
     slug: String,
-}
-
-#[derive(Deserialize)]
-struct GqlLatestReviews {
-    nodes: Vec<GqlReviewNode>,
+    #[serde(rename = "combinedSlug", default)]
+    combined_slug: String,
 }
 
 #[derive(Deserialize)]
 struct GqlReviewNode {
     author: Option<GqlAuthor>,
     state: String,
+    #[serde(default)]
+    commit: Option<GqlOid>,
+}
+
+#[derive(Deserialize)]
+struct GqlOid {
+    oid: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::UserState;
+
+    fn rate_limit(cost: i32, remaining: i32, reset_in: chrono::Duration) -> RateLimit {
+        RateLimit {
+            cost,
+            remaining,
+            reset_at: now() + reset_in,
+        }
+    }
+
+    fn now() -> DateTime<Utc> {
+        "2026-10-17T12:00:00Z".parse().unwrap()
+    }
+
+    #[test]
+    fn backoff_delay_grows_and_is_capped() {
+        for _ in 0..100 {
+            let first = backoff_delay(0);
+            assert!(first >= RETRY_BASE_DELAY / 2 && first <= RETRY_BASE_DELAY);
+            let third = backoff_delay(2);
+            assert!(third >= RETRY_BASE_DELAY * 2 && third <= RETRY_BASE_DELAY * 4);
+            let capped = backoff_delay(u32::MAX);
+            assert!(capped >= RETRY_MAX_DELAY / 2 && capped <= RETRY_MAX_DELAY);
+        }
+    }
+
+    #[test]
+    fn next_sync_delay_without_rate_limit_is_the_interval() {
+        let interval = Duration::from_secs(60);
+        assert_eq!(next_sync_delay(interval, None, now()), interval);
+        let reset = rate_limit(1, 0, chrono::Duration::seconds(-10));
+        assert_eq!(next_sync_delay(interval, Some(&reset), now()), interval);
+    }
+
+    #[test]
+    fn next_sync_delay_never_is_zero() {
+        assert_eq!(next_sync_delay(Duration::ZERO, None, now()), MIN_SYNC_DELAY);
+        let plenty = rate_limit(1, 4000, chrono::Duration::hours(1));
+        assert_eq!(
+            next_sync_delay(Duration::ZERO, Some(&plenty), now()),
+            MIN_SYNC_DELAY
+        );
+    }
+
+    #[test]
+    fn until_due_of_a_query_that_never_ran_is_zero() {
+        assert_eq!(
+            until_due(None, None, None, Duration::from_secs(60), now()),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn until_due_after_success_waits_for_the_interval() {
+        let state = QueryState {
+            synced_at: now() - chrono::Duration::seconds(20),
+            prs: Vec::new(),
+        };
+        assert_eq!(
+            until_due(Some(&state), None, None, Duration::from_secs(60), now()),
+            Duration::from_secs(40)
+        );
+        assert_eq!(
+            until_due(Some(&state), None, None, Duration::from_secs(10), now()),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn until_due_after_failure_waits_for_the_retry() {
+        let failure = QueryFailure {
+            count: 3,
+            kind: ErrorKind::Network,
+            retry_at: now() + chrono::Duration::seconds(30),
+        };
+        // Also if the query never succeeded.
+        assert_eq!(
+            until_due(None, Some(&failure), None, Duration::from_secs(60), now()),
+            Duration::from_secs(30)
+        );
+        let state = QueryState {
+            synced_at: now() - chrono::Duration::hours(1),
+            prs: Vec::new(),
+        };
+        assert_eq!(
+            until_due(
+                Some(&state),
+                Some(&failure),
+                None,
+                Duration::from_secs(60),
+                now()
+            ),
+            Duration::from_secs(30)
+        );
+    }
+
+    #[test]
+    fn until_due_waits_for_the_own_host_only() {
+        // github.com is almost out of budget, the enterprise host has plenty.
+        let github = rate_limit(10, 15, chrono::Duration::minutes(30));
+        let enterprise = rate_limit(10, 4000, chrono::Duration::minutes(30));
+        let held_until = |r: &RateLimit| now() + next_sync_delay(Duration::ZERO, Some(r), now());
+        let state = QueryState {
+            synced_at: now() - chrono::Duration::hours(1),
+            prs: Vec::new(),
+        };
+        let interval = Duration::from_secs(60);
+        assert_eq!(
+            until_due(
+                Some(&state),
+                None,
+                Some(held_until(&github)),
+                interval,
+                now()
+            ),
+            Duration::from_secs(1800)
+        );
+        assert_eq!(
+            until_due(
+                Some(&state),
+                None,
+                Some(held_until(&enterprise)),
+                interval,
+                now()
+            ),
+            MIN_SYNC_DELAY
+        );
+        assert_eq!(
+            until_due(None, None, Some(held_until(&github)), interval, now()),
+            Duration::from_secs(1800)
+        );
+    }
+
+    #[test]
+    fn merge_rate_limits_adds_costs_and_keeps_the_lowest_budget() {
+        let a = rate_limit(3, 4000, chrono::Duration::hours(1));
+        let b = rate_limit(2, 3990, chrono::Duration::hours(1));
+        let merged = merge_rate_limits(Some(a), Some(b)).unwrap();
+        assert_eq!((merged.cost, merged.remaining), (5, 3990));
+        assert!(merge_rate_limits(None, None).is_none());
+    }
+
+    #[test]
+    fn next_sync_delay_spreads_syncs_until_reset() {
+        let interval = Duration::from_secs(60);
+        let plenty = rate_limit(1, 4000, chrono::Duration::hours(1));
+        assert_eq!(next_sync_delay(interval, Some(&plenty), now()), interval);
+        let low = rate_limit(10, 100, chrono::Duration::hours(1));
+        assert_eq!(
+            next_sync_delay(interval, Some(&low), now()),
+            Duration::from_secs(360)
+        );
+    }
+
+    #[test]
+    fn next_sync_delay_pauses_until_reset_when_exhausted() {
+        let interval = Duration::from_secs(60);
+        let exhausted = rate_limit(10, 15, chrono::Duration::minutes(30));
+        assert_eq!(
+            next_sync_delay(interval, Some(&exhausted), now()),
+            Duration::from_secs(1800)
+        );
+        let free = rate_limit(0, 1, chrono::Duration::minutes(30));
+        assert_eq!(
+            next_sync_delay(interval, Some(&free), now()),
+            Duration::from_secs(1800)
+        );
+    }
+
+    /// Answers the GraphQL queries with `respond`, and records the variables of each query.
+    struct FakeTransport<F> {
+        respond: F,
+        calls: Mutex<Vec<serde_json::Value>>,
+    }
+
+    impl<F> FakeTransport<F>
+    where
+        F: Fn(&str, &serde_json::Value) -> Result<serde_json::Value> + Sync,
+    {
+        fn new(respond: F) -> Self {
+            Self {
+                respond,
+                calls: Mutex::new(Vec::new()),
+            }
+        }
+
+        /// Number of the queries with the variable `name`, e.g. "q" for the searches.
+        fn calls_with(&self, name: &str) -> usize {
+            let calls = self.calls.lock().unwrap();
+            calls.iter().filter(|v| v.get(name).is_some()).count()
+        }
+    }
+
+    impl<F> Transport for FakeTransport<F>
+    where
+        F: Fn(&str, &serde_json::Value) -> Result<serde_json::Value> + Sync,
+    {
+        async fn graphql_response(
+            &self,
+            _host: &Host,
+            query: &str,
+            variables: serde_json::Value,
+        ) -> Result<serde_json::Value> {
+            self.calls.lock().unwrap().push(variables.clone());
+            (self.respond)(query, &variables)
+        }
+    }
+
+    #[derive(Default)]
+    struct MemoryStorage {
+        prs: Mutex<Vec<PullRequest>>,
+        sync_status: Mutex<SyncStatus>,
+        departed: Mutex<Vec<DepartedPullRequest>>,
+    }
+
+    impl Storage for MemoryStorage {
+        fn reset_pull_requests(&self, prs: Vec<PullRequest>) -> Result<()> {
+            *self.prs.lock().unwrap() = prs;
+            Ok(())
+        }
+
+        fn get_pull_requests(&self) -> Result<Vec<PullRequest>> {
+            Ok(self.prs.lock().unwrap().clone())
+        }
+
+        fn mark_url_as_opened(&self, _url: &str) -> Result<bool> {
+            unreachable!("not used by the synchronizer")
+        }
+
+        fn mark_url_as_muted(&self, _url: &str) -> Result<()> {
+            unreachable!("not used by the synchronizer")
+        }
+
+        fn get_user_state(&self) -> Result<UserState> {
+            unreachable!("not used by the synchronizer")
+        }
+
+        fn write_user_state(&self, _state: &UserState) -> Result<()> {
+            unreachable!("not used by the synchronizer")
+        }
+
+        fn add_note(&self, _url: &str, _note: &str) -> Result<()> {
+            unreachable!("not used by the synchronizer")
+        }
+
+        fn get_sync_status(&self) -> Result<SyncStatus> {
+            Ok(self.sync_status.lock().unwrap().clone())
+        }
+
+        fn write_sync_status(&self, status: &SyncStatus) -> Result<()> {
+            *self.sync_status.lock().unwrap() = status.clone();
+            Ok(())
+        }
+
+        fn get_departed_pull_requests(&self) -> Result<Vec<DepartedPullRequest>> {
+            Ok(self.departed.lock().unwrap().clone())
+        }
+
+        fn write_departed_pull_requests(&self, departed: &[DepartedPullRequest]) -> Result<()> {
+            *self.departed.lock().unwrap() = departed.to_vec();
+            Ok(())
+        }
+    }
+
+    /// Queries "Author" and "Assignee", in this order.
+    fn two_queries() -> Config {
+        serde_yaml::from_str(
+            r#"
+queries:
+  - github_arg: "--author=@me"
+    query_name: "Author"
+  - github_arg: "--assignee=@me"
+    query_name: "Assignee"
+"#,
+        )
+        .unwrap()
+    }
+
+    /// A PR in the search results.
+    fn node(number: i32) -> serde_json::Value {
+        json!({
+            "__typename": "PullRequest",
+            "author": { "login": "bob" },
+            "body": "",
+            "comments": { "totalCount": 0 },
+            "createdAt": "2026-10-01T00:00:00Z",
+            "id": format!("PR_{}", number),
+            "number": number,
+            "repository": { "name": "r", "nameWithOwner": "o/r" },
+            "title": format!("PR {}", number),
+            "updatedAt": "2026-10-02T00:00:00Z",
+            "url": format!("https://github.com/o/r/pull/{}", number),
+            "state": "OPEN",
+        })
+    }
+
+    fn search_page(
+        nodes: Vec<serde_json::Value>,
+        end_cursor: Option<&str>,
+    ) -> Result<serde_json::Value> {
+        Ok(json!({ "data": {
+            "rateLimit": { "cost": 1, "remaining": 4000, "resetAt": Utc::now() + chrono::Duration::hours(1) },
+            "viewer": { "login": "carol" },
+            "search": {
+                "issueCount": 1000,
+                "pageInfo": { "hasNextPage": end_cursor.is_some(), "endCursor": end_cursor },
+                "nodes": nodes,
+            },
+        }}))
+    }
+
+    /// A PR as stored by an earlier sync of the query.
+    fn stored(number: i32, query_name: &str) -> PullRequest {
+        let mut pr = PullRequest::for_test(number, "bob");
+        pr.id = format!("PR_{}", number);
+        pr.meta.label = query_name.to_string();
+        pr.meta.queries = vec![query_name.to_string()];
+        pr
+    }
+
+    fn stored_numbers<T: Transport>(sync: &Synchronizer<MemoryStorage, T>) -> Vec<(i32, bool)> {
+        let prs = sync.storage.get_pull_requests().unwrap();
+        prs.iter().map(|pr| (pr.number, pr.meta.stale)).collect()
+    }
+
+    fn departed_numbers<T: Transport>(
+        sync: &Synchronizer<MemoryStorage, T>,
+    ) -> Vec<(i32, DepartureReason)> {
+        let departed = sync.storage.get_departed_pull_requests().unwrap();
+        departed.iter().map(|d| (d.pr.number, d.reason)).collect()
+    }
+
+    #[tokio::test]
+    async fn search_follows_the_cursor_until_github_returns_none() {
+        let transport = FakeTransport::new(|_, vars| {
+            match vars["after"].as_str() {
+                None => search_page(vec![node(1)], Some("c1")),
+                // A next page without a cursor ends the search instead of starting it over.
+                Some("c1") => Ok(json!({ "data": {
+                    "search": {
+                        "issueCount": 1000,
+                        "pageInfo": { "hasNextPage": true, "endCursor": null },
+                        "nodes": [node(2)],
+                    },
+                }})),
+                Some(other) => panic!("unexpected cursor {}", other),
+            }
+        });
+        let mut config = two_queries();
+        config.queries.truncate(1);
+        let sync = Synchronizer::new(MemoryStorage::default(), transport);
+        sync.run_once(&config).await.unwrap();
+        assert_eq!(stored_numbers(&sync), vec![(1, false), (2, false)]);
+        assert_eq!(sync.transport.calls_with("q"), 2);
+    }
+
+    #[tokio::test]
+    async fn failed_query_keeps_its_stored_prs_as_stale() {
+        let transport = FakeTransport::new(|_, vars| {
+            if vars["q"].as_str().unwrap().contains("author:") {
+                Err(anyhow::anyhow!("HTTP 502"))
+            } else {
+                search_page(vec![node(2)], None)
+            }
+        });
+        let storage = MemoryStorage::default();
+        storage
+            .reset_pull_requests(vec![stored(1, "Author")])
+            .unwrap();
+        let sync = Synchronizer::new(storage, transport);
+        sync.run_once(&two_queries()).await.unwrap();
+        assert_eq!(stored_numbers(&sync), vec![(1, true), (2, false)]);
+        assert!(departed_numbers(&sync).is_empty());
+        let status = sync.storage.get_sync_status().unwrap();
+        assert!(status.query_errors.contains_key("Author"));
+        assert!(status.last_success.is_some());
+    }
+
+    #[tokio::test]
+    async fn all_queries_failing_fails_the_sync_and_keeps_the_prs() {
+        let transport = FakeTransport::new(|_, _| Err(anyhow::anyhow!("HTTP 502")));
+        let storage = MemoryStorage::default();
+        storage
+            .reset_pull_requests(vec![stored(1, "Author")])
+            .unwrap();
+        let sync = Synchronizer::new(storage, transport);
+        assert!(sync.run_once(&two_queries()).await.is_err());
+        assert_eq!(stored_numbers(&sync), vec![(1, false)]);
+        let status = sync.storage.get_sync_status().unwrap();
+        assert!(status.last_success.is_none());
+        assert!(!status.last_error.is_empty());
+    }
+
+    #[tokio::test]
+    async fn departed_prs_get_their_final_state() {
+        let transport = FakeTransport::new(|_, vars| {
+            if vars.get("ids").is_some() {
+                Ok(json!({
+                    "data": { "nodes": [{ "state": "MERGED" }, null] },
+                    "errors": [{ "type": "NOT_FOUND", "message": "Could not resolve to a node" }],
+                }))
+            } else {
+                search_page(vec![node(2)], None)
+            }
+        });
+        let storage = MemoryStorage::default();
+        storage
+            .reset_pull_requests(vec![
+                stored(1, "Author"),
+                stored(2, "Author"),
+                stored(3, "Author"),
+            ])
+            .unwrap();
+        let sync = Synchronizer::new(storage, transport);
+        let mut config = two_queries();
+        config.queries.truncate(1);
+        sync.run_once(&config).await.unwrap();
+        assert_eq!(stored_numbers(&sync), vec![(2, false)]);
+        assert_eq!(
+            departed_numbers(&sync),
+            vec![(1, DepartureReason::Merged), (3, DepartureReason::Deleted)]
+        );
+    }
+
+    #[tokio::test]
+    async fn queries_that_are_not_due_keep_their_last_results() {
+        let transport = FakeTransport::new(|_, vars| {
+            if vars["q"].as_str().unwrap().contains("author:") {
+                search_page(vec![node(1)], None)
+            } else {
+                search_page(vec![node(2)], None)
+            }
+        });
+        let sync = Synchronizer::new(MemoryStorage::default(), transport);
+        let config = two_queries();
+        sync.run_once(&config).await.unwrap();
+        sync.run_queries(&config, |q| q.query_name == "Assignee")
+            .await
+            .unwrap();
+        assert_eq!(stored_numbers(&sync), vec![(1, false), (2, false)]);
+        assert_eq!(sync.transport.calls_with("q"), 3);
+        assert!(departed_numbers(&sync).is_empty());
+    }
+
+    #[tokio::test]
+    async fn pause_for_the_api_budget_is_written_to_the_sync_status() {
+        let reset_at = Utc::now() + chrono::Duration::minutes(30);
+        let transport = FakeTransport::new(move |_, _| {
+            let mut page = search_page(vec![node(1)], None)?;
+            page["data"]["rateLimit"] = json!({ "cost": 1, "remaining": 1, "resetAt": reset_at });
+            Ok(page)
+        });
+        let sync = Synchronizer::new(MemoryStorage::default(), transport);
+        sync.run_once(&two_queries()).await.unwrap();
+        sync.write_schedule(reset_at).unwrap();
+
+        let status = sync.storage.get_sync_status().unwrap();
+        assert_eq!(status.next_sync_at, Some(reset_at));
+        assert_eq!(
+            status.paused_until.into_iter().collect::<Vec<_>>(),
+            vec![("github.com".to_string(), reset_at)]
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_query_is_retried_before_its_interval() {
+        let transport = FakeTransport::new(|_, vars| {
+            if vars["q"].as_str().unwrap().contains("author:") {
+                Err(anyhow::anyhow!("HTTP 502"))
+            } else {
+                search_page(vec![node(2)], None)
+            }
+        });
+        let sync = Synchronizer::new(MemoryStorage::default(), transport);
+        let config = two_queries();
+        sync.run_once(&config).await.unwrap();
+        let interval = Duration::from_secs(600);
+        let now = Utc::now();
+        let failed = sync.until_due(&config.queries[0], interval, now);
+        assert!(!failed.is_zero() && failed <= RETRY_BASE_DELAY);
+        let succeeded = sync.until_due(&config.queries[1], interval, now);
+        assert!(succeeded > RETRY_BASE_DELAY && succeeded <= interval);
+    }
+
+    #[tokio::test]
+    async fn query_waiting_for_its_retry_keeps_its_stored_prs() {
+        let transport = FakeTransport::new(|_, vars| match vars["q"].as_str() {
+            Some(q) if q.contains("author:") => Err(anyhow::anyhow!("HTTP 502")),
+            Some(_) => search_page(vec![node(2)], None),
+            None => Ok(json!({ "data": { "nodes": [{ "state": "OPEN" }] } })),
+        });
+        // PR 1 was stored by the Author query before a restart, which then fails and waits for its retry while the
+        // Assignee query runs again.
+        let storage = MemoryStorage::default();
+        storage
+            .reset_pull_requests(vec![stored(1, "Author")])
+            .unwrap();
+        let sync = Synchronizer::new(storage, transport);
+        let config = two_queries();
+        sync.run_once(&config).await.unwrap();
+        sync.run_queries(&config, |q| q.query_name == "Assignee")
+            .await
+            .unwrap();
+        assert_eq!(stored_numbers(&sync), vec![(1, true), (2, false)]);
+        assert!(departed_numbers(&sync).is_empty());
+    }
+
+    #[tokio::test]
+    async fn team_query_fails_without_the_teams_and_does_not_retry_them_per_pr() {
+        let transport = FakeTransport::new(|_, vars| {
+            if vars.get("login").is_some() {
+                return Err(anyhow::anyhow!("INSUFFICIENT_SCOPES: read:org"));
+            }
+            let team_pr = |number| {
+                let mut pr = node(number);
+                pr["reviewRequests"] = json!({
+                    "pageInfo": { "hasNextPage": false, "endCursor": null },
+                    "nodes": [{ "requestedReviewer": { "name": "Core", "slug": "core", "combinedSlug": "o/core" } }],
+                });
+                pr
+            };
+            search_page(vec![team_pr(1), team_pr(2)], None)
+        });
+        let storage = MemoryStorage::default();
+        storage
+            .reset_pull_requests(vec![stored(1, "Team")])
+            .unwrap();
+        let sync = Synchronizer::new(storage, transport);
+        let config: Config = serde_yaml::from_str(
+            r#"
+queries:
+  - github_arg: "--review-requested=@me"
+    query_name: "Team"
+    review_requested: team
+"#,
+        )
+        .unwrap();
+        assert!(sync.run_once(&config).await.is_err());
+        assert!(sync.run_once(&config).await.is_err());
+        assert_eq!(stored_numbers(&sync), vec![(1, false)]);
+        assert!(departed_numbers(&sync).is_empty());
+        assert_eq!(sync.transport.calls_with("login"), 1);
+        let status = sync.storage.get_sync_status().unwrap();
+        assert!(status.query_errors["Team"].contains("teams"));
+    }
+
+    /// Answers the review threads batches with one unresolved thread of 3 comments for each PR.
+    fn review_threads(ids: &serde_json::Value) -> Result<serde_json::Value> {
+        let thread = json!({
+            "pageInfo": { "hasNextPage": false, "endCursor": null },
+            "nodes": [{ "isResolved": false, "comments": {
+                "totalCount": 3,
+                "nodes": [{ "author": { "login": "dave" }, "createdAt": "2026-10-01T12:00:00Z" }],
+            }}],
+        });
+        let nodes: Vec<_> = ids
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|_| json!({ "reviewThreads": thread }))
+            .collect();
+        Ok(json!({ "data": {
+            "rateLimit": { "cost": 1, "remaining": 3999, "resetAt": Utc::now() + chrono::Duration::hours(1) },
+            "nodes": nodes,
+        }}))
+    }
+
+    /// A PR in the search results with a review thread.
+    fn node_with_thread(number: i32) -> serde_json::Value {
+        let mut pr = node(number);
+        pr["reviewThreads"] = json!({ "totalCount": 1 });
+        pr
+    }
+
+    #[tokio::test]
+    async fn review_threads_are_reused_only_if_they_were_fetched() {
+        let transport = FakeTransport::new(|query, vars| {
+            if query.contains("reviewThreads(") {
+                review_threads(&vars["ids"])
+            } else {
+                search_page(vec![node_with_thread(1)], None)
+            }
+        });
+        let mut config = two_queries();
+        config.queries.truncate(1);
+        // Stored before the review threads were tracked, with the same updatedAt as in the search.
+        let mut old = stored(1, "Author");
+        old.updated_at = "2026-10-02T00:00:00Z".parse().unwrap();
+        let storage = MemoryStorage::default();
+        storage.reset_pull_requests(vec![old]).unwrap();
+        let sync = Synchronizer::new(storage, transport);
+
+        sync.run_once(&config).await.unwrap();
+        assert_eq!(sync.transport.calls_with("ids"), 1);
+        let prs = sync.storage.get_pull_requests().unwrap();
+        assert!(prs[0].review_threads_fetched);
+        assert_eq!(
+            (prs[0].review_comments_count, prs[0].unresolved_threads),
+            (3, 1)
+        );
+
+        sync.run_once(&config).await.unwrap();
+        assert_eq!(sync.transport.calls_with("ids"), 1);
+        let prs = sync.storage.get_pull_requests().unwrap();
+        assert_eq!(
+            (prs[0].review_comments_count, prs[0].unresolved_threads),
+            (3, 1)
+        );
+        assert_eq!(prs[0].timeline.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn review_threads_are_fetched_in_batches_and_counted_in_the_budget() {
+        let transport = FakeTransport::new(|query, vars| {
+            if query.contains("reviewThreads(") {
+                review_threads(&vars["ids"])
+            } else {
+                search_page((1..=45).map(node_with_thread).collect(), None)
+            }
+        });
+        let mut config = two_queries();
+        config.queries.truncate(1);
+        let sync = Synchronizer::new(MemoryStorage::default(), transport);
+        sync.run_once(&config).await.unwrap();
+        assert_eq!(sync.transport.calls_with("ids"), 3);
+        let prs = sync.storage.get_pull_requests().unwrap();
+        assert!(prs.iter().all(|pr| pr.review_comments_count == 3));
+        let status = sync.storage.get_sync_status().unwrap();
+        let rate_limit = &status.rate_limits["github.com"];
+        assert_eq!((rate_limit.cost, rate_limit.remaining), (4, 3999));
+    }
+
+    #[tokio::test]
+    async fn failed_review_threads_do_not_fail_the_query() {
+        let transport = FakeTransport::new(|query, _| {
+            if query.contains("reviewThreads(") {
+                Err(anyhow::anyhow!("HTTP 502"))
+            } else {
+                search_page(vec![node_with_thread(1)], None)
+            }
+        });
+        let mut config = two_queries();
+        config.queries.truncate(1);
+        let mut fetched = stored(1, "Author");
+        fetched.review_threads_fetched = true;
+        fetched.review_comments_count = 2;
+        fetched.updated_at = "2026-10-01T00:00:00Z".parse().unwrap();
+        let storage = MemoryStorage::default();
+        storage.reset_pull_requests(vec![fetched]).unwrap();
+        let sync = Synchronizer::new(storage, transport);
+        sync.run_once(&config).await.unwrap();
+        let prs = sync.storage.get_pull_requests().unwrap();
+        // The PR changed, so its earlier review threads are kept until they can be fetched.
+        assert!(!prs[0].review_threads_fetched);
+        assert_eq!(prs[0].review_comments_count, 2);
+        assert!(!prs[0].meta.stale);
+    }
 }
@@ -0,0 +1,429 @@
+//! Transports that send GraphQL queries to GitHub. The synchronizer is generic over [Transport], so the `gh` CLI can
+//! be swapped for a direct HTTPS client, or for anything that speaks GraphQL.
+
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::env;
+use std::fmt::{self, Display};
+use std::future::Future;
+use std::io;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+pub const TRANSPORT_GH: &str = "gh";
+pub const TRANSPORT_HTTP: &str = "http";
+pub const DEFAULT_GRAPHQL_URL: &str = "https://api.github.com/graphql";
+pub const DEFAULT_HOST: &str = "github.com";
+
+const ERROR_TYPE_NOT_FOUND: &str = "NOT_FOUND";
+
+const TOKEN_ENV_VARS: [&str; 2] = ["GITHUB_TOKEN", "GH_TOKEN"];
+const ENTERPRISE_TOKEN_ENV_VARS: [&str; 2] = ["GH_ENTERPRISE_TOKEN", "GITHUB_ENTERPRISE_TOKEN"];
+
+/// The GitHub host a query is sent to, and the environment variable with its token. Empty fields mean github.com and
+/// the default credentials of the transport.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Host {
+    pub name: String,
+    pub token_env: String,
+}
+
+impl Host {
+    pub fn new(name: &str, token_env: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            token_env: token_env.to_string(),
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.name.is_empty() || self.name == DEFAULT_HOST
+    }
+
+    /// The token from `token_env`, None if `token_env` is not set, and an error if the variable is missing.
+    fn token_from_env(&self) -> Result<Option<String>> {
+        if self.token_env.is_empty() {
+            return Ok(None);
+        }
+        match env::var(&self.token_env) {
+            Ok(token) if !token.is_empty() => Ok(Some(token)),
+            _ => Err(TransportError::new(
+                ErrorKind::Auth,
+                format!(
+                    "No token for {} in the environment variable {}",
+                    self, self.token_env
+                ),
+            )
+            .into()),
+        }
+    }
+}
+
+impl Display for Host {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.name.is_empty() {
+            write!(f, "{}", DEFAULT_HOST)
+        } else {
+            write!(f, "{}", self.name)
+        }
+    }
+}
+
+/// Kind of a failure, used by the synchronizer to decide whether to retry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// Connection problems, timeouts and server errors.
+    Network,
+    /// Missing or rejected credentials. Retrying does not help.
+    Auth,
+    /// The API budget is exhausted.
+    RateLimit,
+    /// The response could not be parsed.
+    MalformedResponse,
+    /// GitHub understood the request but returned errors, e.g. for an invalid search.
+    Api,
+    /// The query is invalid in the config, e.g. an unknown kind. Retrying does not help.
+    Config,
+}
+
+impl ErrorKind {
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, ErrorKind::Auth | ErrorKind::Config)
+    }
+}
+
+#[derive(Debug)]
+pub struct TransportError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl TransportError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {}", self.kind, self.message)
+    }
+}
+
+impl std::error::Error for TransportError {}
+
+/// Classify any error that came out of a sync. Errors not raised by a transport are treated as malformed
+/// responses if they come from parsing, and as network errors otherwise, so they are retried.
+pub fn classify(err: &anyhow::Error) -> ErrorKind {
+    if let Some(e) = err.downcast_ref::<TransportError>() {
+        e.kind
+    } else if err.downcast_ref::<serde_json::Error>().is_some() {
+        ErrorKind::MalformedResponse
+    } else {
+        ErrorKind::Network
+    }
+}
+
+pub trait Transport: Sync {
+    /// Run a GraphQL query with the given variables on the host and return the whole response, with the `data` and
+    /// the `errors`.
+    fn graphql_response(
+        &self,
+        host: &Host,
+        query: &str,
+        variables: Value,
+    ) -> impl Future<Output = Result<Value>> + Send;
+
+    /// Run a GraphQL query with the given variables on the host and return the `data` part of the response. Fails if
+    /// the response has errors.
+    fn graphql(
+        &self,
+        host: &Host,
+        query: &str,
+        variables: Value,
+    ) -> impl Future<Output = Result<Value>> + Send {
+        async move { extract_data(self.graphql_response(host, query, variables).await?, &[]) }
+    }
+
+    /// Like [Transport::graphql], but keep the partial `data` if all the errors are NOT_FOUND, as for the nodes that
+    /// were deleted or became inaccessible. The missing nodes are null in the data.
+    fn graphql_allow_not_found(
+        &self,
+        host: &Host,
+        query: &str,
+        variables: Value,
+    ) -> impl Future<Output = Result<Value>> + Send {
+        async move {
+            extract_data(
+                self.graphql_response(host, query, variables).await?,
+                &[ERROR_TYPE_NOT_FOUND],
+            )
+        }
+    }
+}
+
+/// Runs `gh api graphql`, relying on the authentication of the `gh` CLI unless the host has a token.
+#[derive(Debug, Clone, Default)]
+pub struct GhCliTransport;
+
+impl Transport for GhCliTransport {
+    async fn graphql_response(&self, host: &Host, query: &str, variables: Value) -> Result<Value> {
+        let body = serde_json::to_vec(&json!({"query": query, "variables": variables}))?;
+
+        let mut command = Command::new("gh");
+        command.args(["api", "graphql", "--input", "-"]);
+        if !host.is_default() {
+            command.args(["--hostname", &host.name]);
+        }
+        if let Some(token) = host.token_from_env()? {
+            let token_var = if host.is_default() {
+                "GH_TOKEN"
+            } else {
+                "GH_ENTERPRISE_TOKEN"
+            };
+            command.env(token_var, token);
+        }
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => {
+                    TransportError::new(ErrorKind::Auth, "gh CLI is not installed")
+                }
+                _ => TransportError::new(ErrorKind::Network, format!("failed to run gh: {}", e)),
+            })?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(&body).await?;
+        }
+        let output = child.wait_with_output().await?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            // gh prints the GraphQL response to stdout even when it fails on GraphQL errors.
+            if let Ok(response) = serde_json::from_slice::<Value>(&output.stdout) {
+                if response.get("errors").is_some() {
+                    return Ok(response);
+                }
+            }
+            return Err(TransportError::new(
+                classify_gh_stderr(&stderr),
+                format!("gh api graphql failed: {}", stderr.trim()),
+            )
+            .into());
+        }
+
+        let response = serde_json::from_slice(&output.stdout)
+            .map_err(|e| TransportError::new(ErrorKind::MalformedResponse, e.to_string()))?;
+        Ok(response)
+    }
+}
+
+/// Calls the GraphQL endpoint over HTTP(S) with a bearer token. The url and token are those of github.com, the
+/// GitHub Enterprise hosts are reached at `https://<host>/api/graphql`.
+#[derive(Debug, Clone)]
+pub struct HttpTransport {
+    client: reqwest::Client,
+    url: String,
+    token: String,
+}
+
+impl HttpTransport {
+    pub fn new(url: &str, token: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.to_string(),
+            token: token.to_string(),
+        }
+    }
+
+    /// Use the url and token from the config, falling back to the public GitHub endpoint and to the token from
+    /// the environment. A missing token is reported only when a query for github.com needs it.
+    pub fn from_config(url: &str, token: &str) -> Result<Self> {
+        let url = if url.is_empty() {
+            DEFAULT_GRAPHQL_URL
+        } else {
+            url
+        };
+        let token = if token.is_empty() {
+            token_from_env_vars(&TOKEN_ENV_VARS).unwrap_or_default()
+        } else {
+            token.to_string()
+        };
+        Ok(Self::new(url, &token))
+    }
+
+    /// The url and the token for the host.
+    fn endpoint(&self, host: &Host) -> Result<(String, String)> {
+        let url = if host.is_default() {
+            self.url.clone()
+        } else {
+            format!("https://{}/api/graphql", host.name)
+        };
+        let (token, env_vars) = match host.token_from_env()? {
+            Some(token) => (Some(token), Vec::new()),
+            None if host.is_default() => (
+                Some(self.token.clone()).filter(|t| !t.is_empty()),
+                TOKEN_ENV_VARS.to_vec(),
+            ),
+            None => (
+                token_from_env_vars(&ENTERPRISE_TOKEN_ENV_VARS),
+                ENTERPRISE_TOKEN_ENV_VARS.to_vec(),
+            ),
+        };
+        let token = token.ok_or_else(|| {
+            TransportError::new(
+                ErrorKind::Auth,
+                format!(
+                    "No token for {} with the http transport, set it in the config, in `token_env` of the query or in one of {:?}",
+                    host, env_vars
+                ),
+            )
+        })?;
+        Ok((url, token))
+    }
+}
+
+fn token_from_env_vars(names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .find_map(|name| env::var(name).ok().filter(|t| !t.is_empty()))
+}
+
+impl Transport for HttpTransport {
+    async fn graphql_response(&self, host: &Host, query: &str, variables: Value) -> Result<Value> {
+        let (url, token) = self.endpoint(host)?;
+        let response = self
+            .client
+            .post(&url)
+            .bearer_auth(&token)
+            .header(reqwest::header::USER_AGENT, "ffgh")
+            .json(&json!({"query": query, "variables": variables}))
+            .send()
+            .await
+            .map_err(|e| TransportError::new(ErrorKind::Network, e.to_string()))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            let kind = match status.as_u16() {
+                429 => ErrorKind::RateLimit,
+                403 if text.to_lowercase().contains("rate limit") => ErrorKind::RateLimit,
+                401 | 403 => ErrorKind::Auth,
+                _ => ErrorKind::Network,
+            };
+            return Err(TransportError::new(
+                kind,
+                format!(
+                    "GraphQL request to {} failed with {}: {}",
+                    url, status, text
+                ),
+            )
+            .into());
+        }
+
+        let response = response
+            .json()
+            .await
+            .map_err(|e| TransportError::new(ErrorKind::MalformedResponse, e.to_string()))?;
+        Ok(response)
+    }
+}
+
+/// Return the `data` of a GraphQL response, or fail if the response has errors other than those of the `ignored`
+/// types.
+fn extract_data(mut response: Value, ignored: &[&str]) -> Result<Value> {
+    if let Some(errors) = response.get("errors").and_then(|e| e.as_array()) {
+        let ignored = |e: &Value| {
+            e.get("type")
+                .and_then(|t| t.as_str())
+                .is_some_and(|t| ignored.contains(&t))
+        };
+        if !errors.iter().all(ignored) {
+            let messages: Vec<&str> = errors
+                .iter()
+                .filter_map(|e| e.get("message").and_then(|m| m.as_str()))
+                .collect();
+            let rate_limited = errors
+                .iter()
+                .any(|e| e.get("type").and_then(|t| t.as_str()) == Some("RATE_LIMITED"));
+            let kind = if rate_limited {
+                ErrorKind::RateLimit
+            } else {
+                ErrorKind::Api
+            };
+            return Err(TransportError::new(
+                kind,
+                format!("GraphQL errors: {}", messages.join("; ")),
+            )
+            .into());
+        }
+    }
+    match response.get_mut("data") {
+        Some(data) if !data.is_null() => Ok(data.take()),
+        _ => Err(TransportError::new(
+            ErrorKind::MalformedResponse,
+            "GraphQL response without data",
+        )
+        .into()),
+    }
+}
+
+fn classify_gh_stderr(stderr: &str) -> ErrorKind {
+    let stderr = stderr.to_lowercase();
+    if stderr.contains("rate limit") {
+        ErrorKind::RateLimit
+    } else if stderr.contains("gh auth login")
+        || stderr.contains("http 401")
+        || stderr.contains("bad credentials")
+    {
+        ErrorKind::Auth
+    } else {
+        ErrorKind::Network
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extract_data_fails_on_errors() {
+        let response = json!({
+            "data": {"search": null},
+            "errors": [{"type": "RATE_LIMITED", "message": "API rate limit exceeded"}],
+        });
+        let err = extract_data(response, &[]).unwrap_err();
+        assert_eq!(classify(&err), ErrorKind::RateLimit);
+    }
+
+    #[test]
+    fn extract_data_keeps_partial_data_with_ignored_errors() {
+        let response = json!({
+            "data": {"nodes": [{"id": "A", "state": "MERGED"}, null]},
+            "errors": [{"type": "NOT_FOUND", "path": ["nodes", 1], "message": "Could not resolve"}],
+        });
+        assert!(extract_data(response.clone(), &[]).is_err());
+        let data = extract_data(response, &[ERROR_TYPE_NOT_FOUND]).unwrap();
+        assert_eq!(data["nodes"][1], Value::Null);
+    }
+
+    #[test]
+    fn extract_data_fails_on_other_errors_with_ignored_ones() {
+        let response = json!({
+            "data": {"nodes": [null, null]},
+            "errors": [
+                {"type": "NOT_FOUND", "message": "Could not resolve"},
+                {"type": "FORBIDDEN", "message": "Resource not accessible"},
+            ],
+        });
+        let err = extract_data(response, &[ERROR_TYPE_NOT_FOUND]).unwrap_err();
+        assert_eq!(classify(&err), ErrorKind::Api);
+    }
+}
@@ -5,10 +5,10 @@ pub fn cycle(curr: &str, values: &[String]) -> String {
             return values[next_index].clone();
         }
     }
-    
+
     if values.is_empty() {
         curr.to_string()
     } else {
         values[0].clone()
     }
-}
\ No newline at end of file
+}
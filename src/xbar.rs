@@ -1,6 +1,10 @@
 use crate::fzf::{is_mute, PrettyDuration};
 use crate::gh::PullRequest;
-use crate::storage::{get_pr_state_flags, UserState, HAS_NEW_COMMENTS, IS_NEW, IS_UPDATED};
+use crate::links::{self, CompiledTemplate};
+use crate::storage::{
+    get_pr_state_flags, get_waiting_on, SyncStatus, UserState, WaitingOn, HAS_NEW_COMMENTS, IS_NEW,
+    IS_UPDATED,
+};
 use chrono::Utc;
 use std::io::Write;
 
@@ -8,6 +12,8 @@ const P_TOT: &str = "%TOT%";
 const P_NEW: &str = "%NEW%";
 const P_UPDATED: &str = "%UPD%";
 const P_COMMENTED: &str = "%COM%";
+const P_CI_FAILING: &str = "%FAIL%";
+const P_WAITING_ON_ME: &str = "%ME%";
 const P_MAX_NEW_UPD_COM: &str = "%MAX_NEW_UPD_COM%";
 const P_SUM_NEW_UPD_COM: &str = "%SUM_NEW_UPD_COM%";
 pub const DEFAULT_FORMAT: &str = "t:%TOT% n:%NEW% u:%UPD% c:%COM%";
@@ -17,18 +23,28 @@ pub fn print_compact_summary<W: Write>(
     prs: &[PullRequest],
     user_state: &UserState,
     format: &str,
+    include_drafts: bool,
+    link_templates: &[CompiledTemplate],
 ) -> Result<(), std::io::Error> {
     let mut new_count = 0;
     let mut updated_count = 0;
     let mut commented_count = 0;
+    let mut ci_failing_count = 0;
+    let mut waiting_on_me_count = 0;
     let mut total_count = 0;
 
     for pr in prs {
-        if is_mute(user_state, pr) {
+        if is_mute(user_state, pr) || (pr.is_draft && !include_drafts) {
             continue;
         }
 
         total_count += 1;
+        if pr.checks.is_failing() {
+            ci_failing_count += 1;
+        }
+        if get_waiting_on(pr) == WaitingOn::Me {
+            waiting_on_me_count += 1;
+        }
         let pr_state = user_state.per_url.get(&pr.url).cloned().unwrap_or_default();
         let flags = get_pr_state_flags(pr, &pr_state);
 
@@ -43,16 +59,18 @@ pub fn print_compact_summary<W: Write>(
 
     let s = fill_placeholders(
         format,
-        &vec![
+        &[
             (P_TOT, format!("{total_count}")),
             (P_NEW, format!("{new_count}")),
             (P_UPDATED, format!("{updated_count}")),
             (P_COMMENTED, format!("{commented_count}")),
+            (P_CI_FAILING, format!("{ci_failing_count}")),
+            (P_WAITING_ON_ME, format!("{waiting_on_me_count}")),
             (
                 P_MAX_NEW_UPD_COM,
                 format!(
                     "{}",
-                    vec![new_count, updated_count, commented_count]
+                    [new_count, updated_count, commented_count]
                         .iter()
                         .max()
                         .unwrap(),
@@ -62,7 +80,7 @@ pub fn print_compact_summary<W: Write>(
                 P_SUM_NEW_UPD_COM,
                 format!(
                     "{}",
-                    vec![new_count, updated_count, commented_count]
+                    [new_count, updated_count, commented_count]
                         .iter()
                         .sum::<i32>(),
                 ),
@@ -79,7 +97,7 @@ pub fn print_compact_summary<W: Write>(
     let mut visible_prs: Vec<&PullRequest> = prs
         .iter()
         .filter(|pr| {
-            if is_mute(user_state, pr) {
+            if is_mute(user_state, pr) || (pr.is_draft && !include_drafts) {
                 return false;
             }
             let pr_state = user_state.per_url.get(&pr.url).cloned().unwrap_or_default();
@@ -87,29 +105,103 @@ pub fn print_compact_summary<W: Write>(
             flags & (IS_NEW | IS_UPDATED | HAS_NEW_COMMENTS) != 0
         })
         .collect();
-    visible_prs.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    visible_prs.sort_by_key(|pr| std::cmp::Reverse(pr.created_at));
 
     let now = Utc::now();
     for pr in &visible_prs {
         let age = PrettyDuration::from_duration(now - pr.created_at);
         let title = pr.title.replace('|', "/");
-        let approved = pr.latest_reviews.iter().any(|r| r.state == "APPROVED");
-        let appr = if approved { " \u{2705}" } else { "" };
+        let appr = if pr.has_changes_requested() {
+            " \u{270B}"
+        } else if pr.is_approved() {
+            " \u{2705}"
+        } else {
+            ""
+        };
+        let merge = if pr.is_conflicting() {
+            " \u{26A0}"
+        } else if pr.is_blocked() {
+            " \u{26D4}"
+        } else {
+            ""
+        };
+        let ci = if pr.checks.is_failing() {
+            " \u{274C}"
+        } else {
+            ""
+        };
         writeln!(
             writer,
-            "[{repo}]{appr} ({age}): {title} | href={url}",
+            "[{repo}]{appr}{merge}{ci} ({age}): {title} | href={url}",
             repo = pr.repository.name,
             url = pr.url,
         )?;
+        // Linked issues and tickets go to a submenu of the PR. With a submenu, clicking the PR item opens the submenu
+        // instead of the PR, so the PR is its first entry.
+        let links = links::ticket_links(link_templates, pr);
+        if !pr.closing_issues.is_empty() || !links.is_empty() {
+            writeln!(writer, "--Open PR | href={}", pr.url)?;
+        }
+        for issue in &pr.closing_issues {
+            writeln!(
+                writer,
+                "--Closes #{}: {} | href={}",
+                issue.number,
+                menu_text(&issue.title),
+                issue.url
+            )?;
+        }
+        for link in links {
+            writeln!(writer, "--{} | href={}", menu_text(&link.text), link.url)?;
+        }
     }
 
     Ok(())
 }
 
+/// Print the menu for PRs that are out of sync, with the reason in the dropdown.
+pub fn print_out_of_sync<W: Write>(
+    writer: &mut W,
+    reason: &str,
+    status: &SyncStatus,
+) -> Result<(), std::io::Error> {
+    writeln!(writer, "GH err!")?;
+    writeln!(writer, "---")?;
+    writeln!(writer, "{} | color=red", menu_text(reason))?;
+    if let Some(last_success) = status.last_success {
+        let age = PrettyDuration::from_duration(Utc::now() - last_success);
+        writeln!(writer, "Last synced {age} ago")?;
+    }
+    print_query_errors(writer, status)
+}
+
+/// Print dropdown items for the hosts whose syncs are paused and the queries that failed in the last sync.
+pub fn print_query_errors<W: Write>(
+    writer: &mut W,
+    status: &SyncStatus,
+) -> Result<(), std::io::Error> {
+    for pause in status.pauses(Utc::now()) {
+        writeln!(writer, "Sync of {} | color=orange", menu_text(&pause))?;
+    }
+    for (query_name, error) in &status.query_errors {
+        writeln!(
+            writer,
+            "Query {query_name} failed: {} | color=red",
+            menu_text(error)
+        )?;
+    }
+    Ok(())
+}
+
+/// Make the text safe for a single xbar menu item.
+fn menu_text(s: &str) -> String {
+    s.replace('|', "/").replace('\n', " ")
+}
+
 fn fill_placeholders(s: &str, fillers: &[(&str, String)]) -> String {
     let mut s = s.to_string();
     for (placeholder, value) in fillers {
-        s = s.replace(placeholder, &value).to_string();
+        s = s.replace(placeholder, value).to_string();
     }
     s
 }